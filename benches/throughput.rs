@@ -0,0 +1,161 @@
+//! Throughput benchmarks: `cargo bench`.
+//!
+//! Dependency-free timing harness; each case reports MB/s of uncompressed data.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+/// Counts allocations and reallocations, to report them per call.
+struct Counting;
+
+static ALLOCS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, l: Layout) -> *mut u8 {
+        ALLOCS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(l)
+    }
+    unsafe fn dealloc(&self, p: *mut u8, l: Layout) { System.dealloc(p, l) }
+    unsafe fn realloc(&self, p: *mut u8, l: Layout, n: usize) -> *mut u8 {
+        ALLOCS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(p, l, n)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+fn allocs(f: impl FnOnce() -> usize) -> usize {
+    let before = ALLOCS.load(Ordering::Relaxed);
+    assert_ne!(f(), 0);
+    ALLOCS.load(Ordering::Relaxed) - before
+}
+
+fn mbps(name: &str, bytes: usize, mut f: impl FnMut() -> usize) {
+    let mut sink = f(); // warm-up
+    let mut iters = 0u32;
+    let start = Instant::now();
+    while start.elapsed() < Duration::from_millis(500) {
+        sink = sink.wrapping_add(f());
+        iters += 1;
+    }
+    let secs = start.elapsed().as_secs_f64();
+    assert_ne!(sink, 0); // keeps the results observable
+    let rate = bytes as f64 * iters as f64 / secs / 1e6;
+    println!("{:<40} {:>10.1} MB/s  ({} iters)", name, rate, iters);
+}
+
+/// 1 MiB, ~99% zeros with a literal every 4 KiB.
+fn mostly_zero() -> Vec<u8> {
+    let mut v = vec![0u8; 1 << 20];
+    for i in (0..v.len()).step_by(4096) { v[i] = 0x42; }
+    v
+}
+
+/// 1 MiB of xorshift noise with 0x00/0xFF remapped, so every byte is a literal.
+fn random_literals() -> Vec<u8> {
+    let mut x = 0x2545_f491_4f6c_dd1du64;
+    (0..1 << 20).map(|_| {
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        match x as u8 { 0x00 => 0x01, 0xFF => 0xFE, b => b }
+    }).collect()
+}
+
+/// 1 MiB of literals interrupted every ~1 KiB by a 0x00 or 0xFF run of 1..=200
+/// bytes, the shape of padded calldata.
+fn mixed() -> Vec<u8> {
+    let mut v = random_literals();
+    let mut x = 0x9e37_79b9u32;
+    let mut i = 0;
+    while i < v.len() {
+        x ^= x << 13;
+        x ^= x >> 17;
+        x ^= x << 5;
+        let n = (x % 200) as usize + 1;
+        let b = if x & 0x100 == 0 { 0x00 } else { 0xFF };
+        for p in &mut v[i..(i + n).min(1 << 20)] { *p = b; }
+        i += n + 512 + (x >> 22) as usize;
+    }
+    v
+}
+
+/// Baseline suite: `compress`/`decompress` on the representative workloads.
+/// Performance changes should quote these numbers before and after.
+fn workloads() {
+    let all_zero = vec![0x00u8; 1 << 20];
+    let all_ff = vec![0xFFu8; 1 << 20];
+    let (random, mixed) = (random_literals(), mixed());
+    for (name, data) in [("all_zero", &all_zero), ("all_ff", &all_ff), ("random_literals", &random), ("mixed", &mixed)] {
+        let comp = cdrle::compress(data);
+        mbps(&format!("compress/{}_1MiB", name), data.len(), || cdrle::compress(data).len());
+        mbps(&format!("decompress/{}_1MiB", name), data.len(), || cdrle::decompress(&comp).unwrap().len());
+    }
+}
+
+fn main() {
+    workloads();
+
+    let zeros = mostly_zero();
+    // Per-byte state machine, for comparison with the bulk run scan in `compress` below.
+    mbps("compress_from_iter/mostly_zero_1MiB", zeros.len(), || {
+        cdrle::compress_from_iter(zeros.iter().copied()).len()
+    });
+
+    // Output sizing: growing from `input.len()` (default) vs an exact `compressed_len`
+    // pre-pass. The pre-pass is a per-byte scan, so it loses on run-heavy data.
+    let alternating: Vec<u8> = (0..1 << 20).map(|i| if i % 3 == 0 { 0x00 } else { 0x42 }).collect();
+    let text: Vec<u8> = (0..1 << 20).map(|i| b"calldata, mostly literal "[i % 25]).collect();
+    for (name, data) in [("mostly_zero_1MiB", &zeros), ("expanding_1MiB", &alternating), ("literal_1MiB", &text)] {
+        mbps(&format!("compress/{}", name), data.len(), || cdrle::compress(data).len());
+        mbps(&format!("compress_exact/{}", name), data.len(), || cdrle::compress_exact(data).len());
+    }
+
+    // Decompression: exact reservation after a `decompressed_len` pre-pass vs the
+    // streaming decoder growing its output.
+    let comp = cdrle::compress(&zeros);
+    let stream = || {
+        let mut d = cdrle::Decompressor::with_capacity(comp.len());
+        d.push(&comp).unwrap();
+        d.finish().unwrap().len()
+    };
+    mbps("decompress/mostly_zero_1MiB", zeros.len(), || cdrle::decompress(&comp).unwrap().len());
+    mbps("Decompressor/mostly_zero_1MiB", zeros.len(), stream);
+    println!("allocations per call: decompress {}, Decompressor {}",
+             allocs(|| cdrle::decompress(&comp).unwrap().len()), allocs(stream));
+
+    // Adjacent max-length zero runs: identical full-length tokens are expanded
+    // with one fill, so this measures the token scan rather than per-run fills.
+    let max_runs = cdrle::compress(&vec![0u8; 4 << 20]);
+    mbps("decompress/max_zero_runs_4MiB", 4 << 20, || cdrle::decompress(&max_runs).unwrap().len());
+    let mut buf = vec![0u8; 4 << 20];
+    mbps("decompress_slice/max_zero_runs_4MiB", 4 << 20, || cdrle::decompress_slice(&max_runs, &mut buf).unwrap());
+
+    // ASCII text with a 0x00 every ~1 KiB: long literal stretches between runs.
+    let mut sparse = text.clone();
+    for i in (0..sparse.len()).step_by(1021) { sparse[i] = 0x00; }
+    mbps("compress/text_sparse_zero_1MiB", sparse.len(), || cdrle::compress(&sparse).len());
+
+    // Many small messages: `compress` in a loop vs `compress_batch` reusing scratch.
+    let msgs: Vec<Vec<u8>> = (0..1000).map(|i| {
+        let mut m = vec![0x42u8; 64 + i % 64];
+        for j in (0..m.len()).step_by(3) { m[j] = 0x00; } // expands, so `compress` grows
+        m
+    }).collect();
+    let inputs: Vec<&[u8]> = msgs.iter().map(|m| &m[..]).collect();
+    let total: usize = msgs.iter().map(|m| m.len()).sum();
+    mbps("compress_loop/1000_messages", total, || inputs.iter().map(|m| cdrle::compress(m).len()).sum());
+    mbps("compress_batch/1000_messages", total, || cdrle::compress_batch(&inputs).len());
+    println!("allocations per batch: compress loop {}, compress_batch {}",
+             allocs(|| inputs.iter().map(|m| cdrle::compress(m).len()).sum()),
+             allocs(|| cdrle::compress_batch(&inputs).len()));
+
+    // A single run takes the fast path in `compress`.
+    let all_zero = vec![0u8; 4 << 20];
+    mbps("compress/all_zero_4MiB", all_zero.len(), || cdrle::compress(&all_zero).len());
+    mbps("compress_from_iter/all_zero_4MiB", all_zero.len(), || {
+        cdrle::compress_from_iter(all_zero.iter().copied()).len()
+    });
+}
@@ -0,0 +1,21 @@
+//! Enables the `allocator_api` feature's code only on a nightly compiler, so
+//! `--all-features` still builds on stable (the feature is then a no-op).
+
+use std::env;
+use std::process::Command;
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rustc-check-cfg=cfg(cdrle_allocator_api)");
+    if env::var_os("CARGO_FEATURE_ALLOCATOR_API").is_none() {
+        return;
+    }
+    let rustc = env::var_os("RUSTC").unwrap_or_else(|| "rustc".into());
+    let version = Command::new(rustc).arg("--version").output().map(|o| o.stdout).unwrap_or_default();
+    let version = String::from_utf8_lossy(&version);
+    if version.contains("nightly") || version.contains("-dev") {
+        println!("cargo:rustc-cfg=cdrle_allocator_api");
+    } else {
+        println!("cargo:warning=cdrle: the `allocator_api` feature needs a nightly compiler; ignored on {}", version.trim());
+    }
+}
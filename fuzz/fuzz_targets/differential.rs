@@ -0,0 +1,34 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+/// Reference encoder written straight from the format description, independent
+/// of the crate: literals are copied, each maximal 0x00/0xFF run is split into
+/// pieces of at most 128/32 bytes, each piece becomes `[0x00, flag | (len - 1)]`,
+/// and the first 4 output bytes are XOR-negated.
+fn reference(input: &[u8]) -> Vec<u8> {
+    let mut out = Vec::new();
+    let mut i = 0;
+    while i < input.len() {
+        let b = input[i];
+        if b != 0x00 && b != 0xFF {
+            out.push(b);
+            i += 1;
+            continue;
+        }
+        let (max, flag) = if b == 0x00 { (128, 0x00) } else { (32, 0x80) };
+        let mut n = 0;
+        while i + n < input.len() && input[i + n] == b && n < max { n += 1; }
+        out.push(0x00);
+        out.push(flag | (n - 1) as u8);
+        i += n;
+    }
+    for b in out.iter_mut().take(4) { *b ^= 0xFF; }
+    out
+}
+
+fuzz_target!(|input: &[u8]| {
+    // Property: compress matches the reference byte for byte, not just up to round-trip
+    let expected = reference(input);
+    assert_eq!(cdrle::compress(input), expected);
+    assert_eq!(cdrle::decompress(&expected).expect("reference output rejected"), input);
+});
@@ -0,0 +1,37 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use cdrle::{compress, compress_slice, compressed_len, decompress_slice, Error};
+
+fuzz_target!(|input: &[u8]| {
+    // Layout: [size lo, size hi, data...]; the 16-bit size picks the output buffer length.
+    if input.len() < 2 { return; }
+    let (size, data) = input.split_at(2);
+    let size = u16::from_le_bytes([size[0], size[1]]) as usize;
+    let needed = compressed_len(data);
+
+    // Property: compress_slice fits exactly when size >= compressed_len, and is then compress(data)
+    let mut buf = vec![0u8; size];
+    match compress_slice(data, &mut buf) {
+        Ok(n) => {
+            assert!(size >= needed);
+            assert_eq!(&buf[..n], &compress(data)[..]);
+        }
+        Err(Error::OutputTooSmall { needed: m }) => {
+            assert!(size < needed);
+            assert_eq!(m, needed);
+            return;
+        }
+        Err(e) => panic!("unexpected error {:?}", e),
+    }
+
+    // Property: decompress_slice round-trips into an exactly sized buffer, and reports a
+    // one-short buffer as OutputTooSmall
+    let comp = &buf[..needed];
+    let mut out = vec![0u8; data.len()];
+    assert_eq!(decompress_slice(comp, &mut out), Ok(data.len()));
+    assert_eq!(out, data);
+    if !data.is_empty() {
+        let short = &mut out[..data.len() - 1];
+        assert_eq!(decompress_slice(comp, short), Err(Error::OutputTooSmall { needed: data.len() }));
+    }
+});
@@ -0,0 +1,36 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use cdrle::{compress, Compressor, Decompressor};
+
+/// Splits `data` into chunks of the given sizes (cycled; 0 means an empty chunk).
+fn chunks<'a>(mut data: &'a [u8], sizes: &[u8]) -> Vec<&'a [u8]> {
+    let mut out = Vec::new();
+    for &n in sizes.iter().cycle() {
+        if data.is_empty() { break; }
+        let (head, rest) = data.split_at((n as usize).min(data.len()));
+        out.push(head);
+        data = rest;
+    }
+    out.push(data); // trailing (possibly empty) chunk
+    out
+}
+
+fuzz_target!(|input: &[u8]| {
+    // Layout: [k, k split sizes..., data...]. At least one non-zero size keeps the split finite.
+    let (k, rest) = match input.split_first() { Some((&k, r)) => (k as usize, r), None => return };
+    let (sizes, data) = rest.split_at(k.min(rest.len()));
+    if !sizes.iter().any(|&n| n != 0) { return; }
+
+    // Property: Compressor over any chunking == compress(data)
+    let expected = compress(data);
+    let mut c = Compressor::new();
+    for chunk in chunks(data, sizes) { c.push(chunk); }
+    assert_eq!(c.finish(), expected);
+
+    // Property: Decompressor over any chunking of compress(data) == data
+    let mut d = Decompressor::new();
+    for chunk in chunks(&expected, sizes) {
+        d.push(chunk).expect("decompress failed on compressor output");
+    }
+    assert_eq!(d.finish().expect("dangling marker on compressor output"), data);
+});
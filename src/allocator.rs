@@ -0,0 +1,59 @@
+//! Vec-returning APIs with a caller-chosen allocator (nightly `allocator_api`).
+
+use alloc::vec::Vec;
+use core::alloc::Allocator;
+
+use crate::decode::Decoder;
+use crate::{compress_to_sink, Error};
+
+/// Like `compress`, but allocates the output with `alloc`.
+pub fn compress_in<A: Allocator>(input: &[u8], alloc: A) -> Vec<u8, A> {
+    let mut out = Vec::with_capacity_in(input.len(), alloc);
+    compress_to_sink(input, &mut out);
+    out
+}
+
+/// Like `decompress`, but allocates the output with `alloc`.
+///
+/// Errors: as `decompress`.
+pub fn decompress_in<A: Allocator>(comp: &[u8], alloc: A) -> Result<Vec<u8, A>, Error> {
+    let mut out = Vec::with_capacity_in(comp.len(), alloc);
+    let mut dec = Decoder::default();
+    dec.push(comp, &mut out)?;
+    dec.finish()?;
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{compress, decompress};
+    use alloc::alloc::Global;
+    use core::alloc::{AllocError, Layout};
+    use core::cell::Cell;
+    use core::ptr::NonNull;
+
+    /// Counts allocations made through it.
+    struct Counting<'a>(&'a Cell<usize>);
+
+    unsafe impl Allocator for Counting<'_> {
+        fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+            self.0.set(self.0.get() + 1);
+            Global.allocate(layout)
+        }
+        unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+            Global.deallocate(ptr, layout)
+        }
+    }
+
+    #[test] fn custom_allocator_roundtrip() {
+        let v = [0,0,0,0, 42, 0xFF,0xFF,0xFF, 1,2,3, 0, 0xFF, 0, 0xAA,0xBB, 0, 0xFF];
+        let n = Cell::new(0);
+        let c = compress_in(&v, Counting(&n));
+        assert_eq!(&c[..], &compress(&v)[..]);
+        let d = decompress_in(&c, Counting(&n)).unwrap();
+        assert_eq!(&d[..], &v[..]);
+        assert!(n.get() >= 2);
+        assert_eq!(decompress_in(&[1, 0xFF], Global).map(|d| d.len()), decompress(&[1, 0xFF]).map(|d| d.len()));
+    }
+}
@@ -0,0 +1,90 @@
+//! Compressed streams with a CRC32 integrity trailer.
+
+use alloc::vec::Vec;
+
+use crate::{compress, decompress, Error};
+
+/// CRC-32/ISO-HDLC (the zlib/PNG CRC): reflected polynomial 0xEDB88320.
+const TABLE: [u32; 256] = {
+    let mut t = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut c = i as u32;
+        let mut k = 0;
+        while k < 8 {
+            c = if c & 1 != 0 { 0xEDB8_8320 ^ (c >> 1) } else { c >> 1 };
+            k += 1;
+        }
+        t[i] = c;
+        i += 1;
+    }
+    t
+};
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut c = !0u32;
+    for &b in data {
+        c = TABLE[((c ^ b as u32) & 0xFF) as usize] ^ (c >> 8);
+    }
+    !c
+}
+
+/// `compress(input)` followed by the little-endian CRC32 of `input` (the
+/// uncompressed bytes, so corruption anywhere is caught after decoding).
+/// Decode with `decompress_checked`.
+pub fn compress_checked(input: &[u8]) -> Vec<u8> {
+    let mut out = compress(input);
+    out.extend_from_slice(&crc32(input).to_le_bytes());
+    out
+}
+
+/// Decodes the output of `compress_checked` and verifies its CRC32.
+///
+/// Errors: as `decompress`, plus
+/// - TruncatedFrame                     (shorter than the 4-byte trailer)
+/// - ChecksumMismatch{expected, found}  (`expected` is the stored CRC, `found` the computed one)
+pub fn decompress_checked(comp: &[u8]) -> Result<Vec<u8>, Error> {
+    if comp.len() < 4 {
+        return Err(Error::TruncatedFrame);
+    }
+    let (body, trailer) = comp.split_at(comp.len() - 4);
+    let expected = u32::from_le_bytes([trailer[0], trailer[1], trailer[2], trailer[3]]);
+    let out = decompress(body)?;
+    let found = crc32(&out);
+    if found != expected {
+        return Err(Error::ChecksumMismatch { expected, found });
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test] fn crc32_check_value() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+        assert_eq!(crc32(b""), 0);
+    }
+    #[test] fn checked_roundtrip() {
+        let v = [0,0,0,0, 42, 0xFF,0xFF,0xFF, 1,2,3, 0, 0xFF, 0, 0xAA,0xBB, 0, 0xFF];
+        let c = compress_checked(&v);
+        assert_eq!(&c[..c.len() - 4], &compress(&v)[..]);
+        assert_eq!(decompress_checked(&c).unwrap(), v);
+        assert_eq!(decompress_checked(&compress_checked(&[])).unwrap(), []);
+    }
+    #[test] fn checked_detects_flips() {
+        let v = [0,0,0,0, 42, 0xFF,0xFF,0xFF, 1,2,3, 0, 0xFF, 0, 0xAA,0xBB, 0, 0xFF];
+        let c = compress_checked(&v);
+        for i in 0..c.len() {
+            for bit in 0..8 {
+                let mut bad = c.clone();
+                bad[i] ^= 1 << bit;
+                assert!(decompress_checked(&bad).is_err(), "flip {}:{}", i, bit);
+            }
+        }
+        assert_eq!(decompress_checked(&[1, 2, 3]), Err(Error::TruncatedFrame));
+        let mut bad = c.clone();
+        bad[6] ^= 0x01; // literal 2 -> 3 keeps the stream valid
+        assert!(matches!(decompress_checked(&bad), Err(Error::ChecksumMismatch { .. })));
+    }
+}
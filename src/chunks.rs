@@ -0,0 +1,136 @@
+//! Callback-driven decoding into non-contiguous storage.
+
+use core::fmt;
+
+use crate::scan::literal_len;
+use crate::{decode_control, decode_tokens, Error, Token, MAX_FF_RUN, MAX_ZERO_RUN};
+
+/// Error of [`decompress_chunks`]: the stream was malformed, or the sink failed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DecodeOrSink<E> {
+    /// The stream is malformed; the sink received every chunk before the bad token.
+    Decode(Error),
+    /// The sink returned an error; decoding stopped right after that chunk.
+    Sink(E),
+}
+
+impl<E> From<Error> for DecodeOrSink<E> {
+    fn from(e: Error) -> Self { DecodeOrSink::Decode(e) }
+}
+
+impl<E: fmt::Display> fmt::Display for DecodeOrSink<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeOrSink::Decode(e) => e.fmt(f),
+            DecodeOrSink::Sink(e) => write!(f, "sink error: {}", e),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+impl<E: std::error::Error + 'static> std::error::Error for DecodeOrSink<E> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            DecodeOrSink::Decode(e) => Some(e),
+            DecodeOrSink::Sink(e) => Some(e),
+        }
+    }
+}
+
+static ZEROS: [u8; MAX_ZERO_RUN] = [0x00; MAX_ZERO_RUN];
+static FFS: [u8; MAX_FF_RUN] = [0xFF; MAX_FF_RUN];
+
+/// Decodes `comp`, handing the output to `sink` chunk by chunk instead of
+/// building one contiguous buffer: each literal stretch is passed as a slice of
+/// `comp` itself (bytes in the negated header one at a time), and each run as
+/// a slice of a static buffer of its byte. Concatenating the chunks gives
+/// `decompress(comp)`. Allocates nothing.
+///
+/// Errors:
+/// - Decode(e)  (as `decompress`, apart from its output cap)
+/// - Sink(e)    (the first error `sink` returned)
+pub fn decompress_chunks<E, F>(comp: &[u8], mut sink: F) -> Result<(), DecodeOrSink<E>>
+where
+    F: FnMut(&[u8]) -> Result<(), E>,
+{
+    // Tokens in the negated header: the token iterator un-negates them.
+    let mut toks = decode_tokens(comp);
+    while toks.offset() < 4 {
+        let t = match toks.next() {
+            None => return Ok(()),
+            Some(t) => t?,
+        };
+        match t {
+            Token::Literal(b) => sink(&[b]),
+            Token::ZeroRun(n) => sink(&ZEROS[..n]),
+            Token::FfRun(n) => sink(&FFS[..n]),
+        }
+        .map_err(DecodeOrSink::Sink)?;
+    }
+    // Past the header, literal stretches are contiguous in `comp`.
+    let mut i = toks.offset();
+    while i < comp.len() {
+        let n = literal_len(&comp[i..], 0x00, 0x00);
+        if n != 0 {
+            sink(&comp[i..i + n]).map_err(DecodeOrSink::Sink)?;
+            i += n;
+            continue;
+        }
+        let c = *comp.get(i + 1).ok_or(Error::RunMarkerWithoutControl)?;
+        let (is_ff, len) = decode_control(c);
+        let run = if !is_ff {
+            &ZEROS[..len]
+        } else if len <= MAX_FF_RUN {
+            &FFS[..len]
+        } else {
+            return Err(Error::InvalidRunLength { len }.into());
+        };
+        sink(run).map_err(DecodeOrSink::Sink)?;
+        i += 2;
+    }
+    Ok(())
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use super::*;
+    use crate::{compress, decompress};
+    use alloc::vec::Vec;
+
+    #[test] fn chunks_concatenate_to_decompress() {
+        let mut v = alloc::vec![0u8; 300];
+        v.extend_from_slice(&[0xFF; 70]);
+        v.extend_from_slice(&[0,0,0,0, 42, 0xFF,0xFF,0xFF, 1,2,3, 0, 0xFF, 0, 0xAA,0xBB, 0, 0xFF]);
+        v.extend(1..=200u8);
+        for v in [&v[..], &[], &[7], &[1, 2, 3, 4, 5, 6], &v[370..]] {
+            let c = compress(v);
+            let mut chunks: Vec<Vec<u8>> = Vec::new();
+            decompress_chunks(&c, |s| { chunks.push(s.to_vec()); Ok::<_, ()>(()) }).unwrap();
+            assert_eq!(chunks.concat(), v);
+            assert!(chunks.iter().all(|s| !s.is_empty()));
+        }
+        // A literal stretch past the header arrives as one chunk.
+        let c = compress(&[0, 0, 0, 0, 0, 0, 0xFF, 1, 2, 3, 4, 5]);
+        let mut lens = Vec::new();
+        decompress_chunks(&c, |s| { lens.push(s.len()); Ok::<_, ()>(()) }).unwrap();
+        assert_eq!(lens, [6, 1, 5]);
+    }
+    #[test] fn chunks_errors_compose() {
+        let mut seen = Vec::new();
+        let bad = [1 ^ 0xFF, 2 ^ 0xFF, 3 ^ 0xFF, 4 ^ 0xFF, 5, 0x00, 0xA0];
+        let r = decompress_chunks(&bad, |s| { seen.extend_from_slice(s); Ok::<_, ()>(()) });
+        assert_eq!(r, Err(DecodeOrSink::Decode(Error::InvalidRunLength { len: 33 })));
+        assert_eq!(seen, [1, 2, 3, 4, 5]);
+        let r = decompress_chunks(&[1 ^ 0xFF, 0xFF], |_| Ok::<_, ()>(()));
+        assert_eq!(r, Err(DecodeOrSink::Decode(Error::RunMarkerWithoutControl)));
+        assert_eq!(decompress(&[1 ^ 0xFF, 2 ^ 0xFF, 3 ^ 0xFF, 4 ^ 0xFF, 0x00]), Err(Error::RunMarkerWithoutControl));
+        let r = decompress_chunks(&[1 ^ 0xFF, 2 ^ 0xFF, 3 ^ 0xFF, 4 ^ 0xFF, 0x00], |_| Ok::<_, ()>(()));
+        assert_eq!(r, Err(DecodeOrSink::Decode(Error::RunMarkerWithoutControl)));
+
+        let mut calls = 0;
+        let r = decompress_chunks(&compress(&[1, 0, 2, 0, 3]), |_| { calls += 1; if calls == 2 { Err("full") } else { Ok(()) } });
+        assert_eq!(r, Err(DecodeOrSink::Sink("full")));
+        assert_eq!(calls, 2);
+    }
+}
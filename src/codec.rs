@@ -0,0 +1,43 @@
+//! Object-safe codec traits, for choosing a compression scheme at runtime.
+
+use alloc::vec::Vec;
+
+use crate::{compress, decompress, Error};
+
+/// A one-shot compressor usable as `Box<dyn Encoder>`.
+pub trait Encoder {
+    fn encode(&self, input: &[u8]) -> Vec<u8>;
+}
+
+/// A one-shot decompressor usable as `Box<dyn Decoder>`.
+pub trait Decoder {
+    fn decode(&self, comp: &[u8]) -> Result<Vec<u8>, Error>;
+}
+
+/// The standard format as a codec value: `encode` is [`compress`], `decode` is [`decompress`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Cdrle;
+
+impl Encoder for Cdrle {
+    fn encode(&self, input: &[u8]) -> Vec<u8> { compress(input) }
+}
+
+impl Decoder for Cdrle {
+    fn decode(&self, comp: &[u8]) -> Result<Vec<u8>, Error> { decompress(comp) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::boxed::Box;
+
+    #[test] fn usable_as_trait_objects() {
+        let v = [0,0,0,0, 42, 0xFF,0xFF,0xFF, 1,2,3, 0, 0xFF, 0, 0xAA,0xBB, 0, 0xFF];
+        let enc: Box<dyn Encoder> = Box::new(Cdrle);
+        let dec: Box<dyn Decoder> = Box::new(Cdrle);
+        let c = enc.encode(&v);
+        assert_eq!(c, compress(&v));
+        assert_eq!(dec.decode(&c).unwrap(), v);
+        assert_eq!(dec.decode(&[1, 0xFF]), Err(Error::RunMarkerWithoutControl));
+    }
+}
@@ -0,0 +1,89 @@
+//! `const fn` encoding, for compressing blobs at compile time.
+
+use crate::{MAX_FF_RUN, MAX_ZERO_RUN};
+
+/// Returns the exact length `compress` would produce for `input`, without allocating.
+///
+/// A `const fn`, so it can size the output array of [`compress_const`].
+pub const fn compressed_len(input: &[u8]) -> usize {
+    let mut n = 0;
+    let mut i = 0;
+    while i < input.len() {
+        let b = input[i];
+        if b != 0x00 && b != 0xFF {
+            n += 1;
+            i += 1;
+            continue;
+        }
+        let start = i;
+        while i < input.len() && input[i] == b { i += 1; }
+        let max = if b == 0x00 { MAX_ZERO_RUN } else { MAX_FF_RUN };
+        n += 2 * ((i - start + max - 1) / max);
+    }
+    n
+}
+
+/// `compress` as a `const fn`, for baking compressed blobs into a binary:
+///
+/// ```
+/// const RAW: [u8; 40] = [0; 40];
+/// const BLOB: [u8; cdrle::compressed_len(&RAW)] = cdrle::compress_const(&RAW);
+/// assert_eq!(&BLOB[..], &cdrle::compress(&RAW)[..]);
+/// ```
+///
+/// Panics (a compile error in const context) unless `M == compressed_len(input)`.
+pub const fn compress_const<const N: usize, const M: usize>(input: &[u8; N]) -> [u8; M] {
+    assert!(M == compressed_len(input), "M must equal compressed_len(input)");
+    let mut out = [0u8; M];
+    let mut w = 0;
+    let mut i = 0;
+    while i < N {
+        let b = input[i];
+        if b != 0x00 && b != 0xFF {
+            out[w] = b;
+            w += 1;
+            i += 1;
+            continue;
+        }
+        let start = i;
+        while i < N && input[i] == b { i += 1; }
+        let (max, flag) = if b == 0x00 { (MAX_ZERO_RUN, 0x00) } else { (MAX_FF_RUN, 0x80) };
+        let mut n = i - start;
+        while n != 0 {
+            let k = if n < max { n } else { max };
+            out[w] = 0x00;
+            out[w + 1] = ((k - 1) as u8 & 0x7f) | flag;
+            w += 2;
+            n -= k;
+        }
+    }
+    let mut j = 0;
+    while j < 4 && j < M {
+        out[j] ^= 0xFF;
+        j += 1;
+    }
+    out
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use super::*;
+    use crate::compress;
+
+    const RAW: [u8; 18] = [0,0,0,0, 42, 0xFF,0xFF,0xFF, 1,2,3, 0, 0xFF, 0, 0xAA,0xBB, 0, 0xFF];
+    const BLOB: [u8; compressed_len(&RAW)] = compress_const(&RAW);
+
+    #[test] fn const_matches_compress() {
+        assert_eq!(&BLOB[..], &compress(&RAW)[..]);
+        let mut long = [0u8; 400];
+        long[200..300].fill(0xFF);
+        long[399] = 9;
+        let c: [u8; 15] = compress_const(&long);
+        assert_eq!(&c[..], &compress(&long)[..]);
+        let e: [u8; 0] = compress_const(&[]);
+        assert_eq!(e, [0u8; 0]);
+    }
+    #[test] #[should_panic] fn const_wrong_size() {
+        let _: [u8; 3] = compress_const(&[0; 10]);
+    }
+}
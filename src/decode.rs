@@ -0,0 +1,121 @@
+//! Decoding state machine shared by every decompression entry point.
+
+use crate::sink::Sink;
+use crate::{Error, RleConfig, DEFAULT_MAX_OUTPUT};
+
+/// Stream position and dangling-marker state, so input may arrive in pieces.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Decoder {
+    pos: usize,      // compressed bytes consumed so far
+    marker: bool,    // a 0x00 run marker is waiting for its CONTROL byte
+    produced: usize, // decoded bytes emitted so far
+    limit: usize,    // caller-supplied cap on `produced`
+    cap: usize,      // built-in safety cap on `produced`
+    neg: usize,      // length of the negated stream prefix
+    cfg: RleConfig,
+}
+
+impl Default for Decoder {
+    fn default() -> Self { Self::with_limit(usize::MAX).capped(DEFAULT_MAX_OUTPUT) }
+}
+
+impl Decoder {
+    /// A decoder that fails with `OutputLimitExceeded` rather than emit more than `limit` bytes.
+    /// Unlike `default()`, it has no safety cap: the caller bounds the output.
+    pub(crate) fn with_limit(limit: usize) -> Self {
+        Self { pos: 0, marker: false, produced: 0, limit, cap: usize::MAX, neg: 4, cfg: RleConfig::new() }
+    }
+
+    /// Fails with `OutputTooLarge` rather than emit more than `cap` bytes.
+    pub(crate) const fn capped(mut self, cap: usize) -> Self {
+        self.cap = cap;
+        self
+    }
+
+    /// Decodes runs of the bytes chosen by `cfg` instead of 0x00/0xFF.
+    #[cfg(feature = "alloc")]
+    pub(crate) fn config(mut self, cfg: RleConfig) -> Self {
+        self.cfg = cfg;
+        self
+    }
+
+    /// Expects the first `neg` stream bytes to be negated (4 in the standard format).
+    #[cfg(feature = "alloc")]
+    pub(crate) fn negating(mut self, neg: usize) -> Self {
+        self.neg = neg;
+        self
+    }
+
+    /// Decodes `comp`. A trailing run marker stays pending until the next `push`.
+    ///
+    /// A maximum-length run followed by identical run tokens (what `compress`
+    /// emits for long runs) is expanded with a single `push_fill`.
+    pub(crate) fn push<S: Sink>(&mut self, comp: &[u8], out: &mut S) -> Result<(), Error> {
+        let mut i = 0;
+        while i < comp.len() {
+            let b = comp[i];
+            i += 1;
+            let b = if self.pos < self.neg { b ^ 0xFF } else { b };
+            self.pos += 1;
+            if self.marker {
+                self.marker = false;
+                let slot = (b >> 7) as usize;
+                let len = (b & 0x7F) as usize + 1;
+                // The `+ 1` makes a zero-length run unrepresentable; every run fills something.
+                debug_assert!(len >= 1);
+                let fill = run_byte(&self.cfg, slot, len)?;
+                self.reserve(len)?;
+                let mut n = len;
+                if len == self.cfg.max[slot] && self.pos >= self.neg {
+                    // Past the header, a repeat of this token is the same two raw bytes.
+                    let token = [self.cfg.bytes[0], b];
+                    while comp.get(i..i + 2) == Some(&token[..]) && self.reserve(len).is_ok() {
+                        i += 2;
+                        self.pos += 2;
+                        n += len;
+                    }
+                }
+                out.push_fill(fill, n);
+            } else if b == self.cfg.bytes[0] {
+                self.marker = true;
+            } else {
+                self.reserve(1)?;
+                out.push_byte(b);
+            }
+        }
+        Ok(())
+    }
+
+    /// Accounts for `n` more output bytes, checked before they are written.
+    #[inline]
+    fn reserve(&mut self, n: usize) -> Result<(), Error> {
+        if n > self.limit - self.produced {
+            return Err(Error::OutputLimitExceeded { limit: self.limit });
+        }
+        if n > self.cap - self.produced {
+            return Err(Error::OutputTooLarge { max: self.cap });
+        }
+        self.produced += n;
+        Ok(())
+    }
+
+    /// Checks that the stream did not end on a dangling run marker.
+    #[inline]
+    pub(crate) fn finish(&self) -> Result<(), Error> {
+        if self.marker {
+            return Err(Error::RunMarkerWithoutControl);
+        }
+        Ok(())
+    }
+}
+
+/// Checks a run of `len` bytes of `cfg.bytes[slot]` against its cap and
+/// returns the run byte. The one run check of every decode path, shared by
+/// `Decoder::push` and [`apply_run`](crate::apply_run).
+#[inline]
+pub(crate) fn run_byte(cfg: &RleConfig, slot: usize, len: usize) -> Result<u8, Error> {
+    if len == 0 || len > cfg.max[slot] {
+        return Err(Error::InvalidRunLength { len });
+    }
+    Ok(cfg.bytes[slot])
+}
@@ -0,0 +1,128 @@
+//! Run-encoding state machine shared by every compression entry point.
+
+use crate::scan::{literal_len, run_len};
+use crate::sink::Sink;
+use crate::RleConfig;
+
+/// Pending-run state. Only one run is ever open: switching run bytes emits
+/// the open run first.
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct Encoder {
+    cfg: RleConfig,
+    slot: usize,    // CONTROL bit7 of the open run: 0 = cfg.bytes[0], 1 = cfg.bytes[1]
+    pending: usize, // length of the open run (0 = none)
+    runs: [usize; 2], // runs emitted so far, per slot
+}
+
+impl Encoder {
+    #[cfg(feature = "alloc")]
+    pub(crate) fn new(cfg: RleConfig) -> Self {
+        Self { cfg, slot: 0, pending: 0, runs: [0; 2] }
+    }
+
+    /// Runs emitted so far: `[marker byte runs, other byte runs]`.
+    #[cfg(feature = "alloc")]
+    pub(crate) fn runs(&self) -> [usize; 2] { self.runs }
+
+    /// Run-encodes `input`. Runs still open at the end of `input` stay pending.
+    ///
+    /// Equivalent to `push_byte` per byte, but measures each run in bulk and
+    /// emits its full-length pieces directly instead of counting byte by byte,
+    /// and copies each literal stretch with one `push_slice`.
+    pub(crate) fn push<S: Sink>(&mut self, input: &[u8], out: &mut S) {
+        let mut i = 0;
+        while i < input.len() {
+            let b = input[i];
+            let slot = if b == self.cfg.bytes[0] {
+                0
+            } else if b == self.cfg.bytes[1] {
+                1
+            } else {
+                let n = literal_len(&input[i..], self.cfg.bytes[0], self.cfg.bytes[1]);
+                self.flush(out);
+                out.push_slice(&input[i..i + n]);
+                i += n;
+                continue;
+            };
+            let n = run_len(&input[i..], b);
+            i += n;
+            if self.slot != slot { self.flush(out); self.slot = slot; }
+            self.pending += n;
+            let max = self.cfg.max[slot];
+            while self.pending >= max {
+                self.pending -= max;
+                self.emit_run(out, max);
+            }
+        }
+    }
+
+    #[cfg(feature = "alloc")]
+    #[inline]
+    pub(crate) fn push_byte<S: Sink>(&mut self, b: u8, out: &mut S) {
+        let slot = if b == self.cfg.bytes[0] {
+            0
+        } else if b == self.cfg.bytes[1] {
+            1
+        } else {
+            self.flush(out);
+            out.push_byte(b);
+            return;
+        };
+        if self.slot != slot { self.flush(out); self.slot = slot; }
+        self.pending += 1;
+        if self.pending == self.cfg.max[slot] { self.flush(out); }
+    }
+
+    /// Emits the pending run, if any.
+    #[inline]
+    pub(crate) fn flush<S: Sink>(&mut self, out: &mut S) {
+        if self.pending != 0 {
+            let n = self.pending;
+            self.pending = 0;
+            self.emit_run(out, n);
+        }
+    }
+
+    /// Emits a run of `n` bytes of the current slot.
+    #[inline]
+    fn emit_run<S: Sink>(&mut self, out: &mut S, n: usize) {
+        debug_assert!(n >= 1 && n <= self.cfg.max[self.slot]);
+        let ctrl = ((n - 1) as u8 & 0x7f) | (self.slot as u8) << 7;
+        self.runs[self.slot] += 1;
+        out.push_slice(&[self.cfg.bytes[0], ctrl]); // run marker, CONTROL
+    }
+}
+
+/// The default-format encoding of `n` copies of run byte `b` (0x00 or 0xFF),
+/// header negation included: a fast path that skips the scan for inputs that
+/// are one long run, with the same split as `Encoder::push`.
+#[cfg(feature = "alloc")]
+pub(crate) fn encode_single_run(b: u8, n: usize) -> alloc::vec::Vec<u8> {
+    let (slot, max) = if b == 0x00 { (0u8, crate::MAX_ZERO_RUN) } else { (1, crate::MAX_FF_RUN) };
+    let pair = |len: usize| [0x00, (len - 1) as u8 | slot << 7];
+    let mut out = pair(max).repeat(n / max);
+    if n % max != 0 { out.extend_from_slice(&pair(n % max)); }
+    negate_header(&mut out);
+    out
+}
+
+/// Ends a block (see `Compressor::flush_block`): a run marker with CONTROL 0xFF,
+/// an 0xFF-run of 128 that no standard stream contains.
+#[cfg(feature = "alloc")]
+pub(crate) const BLOCK_END: [u8; 2] = [0x00, 0xFF];
+
+// BLOCK_END is only unambiguous while 0xFF-runs of 128 stay invalid.
+#[cfg(feature = "alloc")]
+const _: () = assert!(crate::MAX_FF_RUN < 128, "BLOCK_END must not be a valid run");
+
+/// XOR-negates the first 4 bytes of a finished compressed stream.
+#[inline]
+#[cfg(feature = "alloc")]
+pub(crate) fn negate_header(out: &mut [u8]) { negate_prefix(out, 4); }
+
+/// XOR-negates the first `n` bytes (or all, if shorter) of a finished compressed stream.
+#[inline]
+#[cfg(feature = "alloc")]
+pub(crate) fn negate_prefix(out: &mut [u8], n: usize) {
+    for b in out.iter_mut().take(n) { *b ^= 0xFF; }
+}
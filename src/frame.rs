@@ -0,0 +1,108 @@
+//! Length-prefixed frames: `varint(len) ++ bytes`, repeated.
+//!
+//! Lengths are unsigned LEB128 (7 bits per byte, low group first, bit7 set on
+//! every byte but the last).
+
+use alloc::vec::Vec;
+
+use crate::{compress, decompress, Error};
+
+/// Compresses each frame independently and concatenates them as
+/// `varint(compressed_len) ++ compress(frame)`. Decode with `decode_frames`.
+pub fn encode_frames(frames: &[&[u8]]) -> Vec<u8> {
+    let mut out = Vec::new();
+    for f in frames {
+        put_frame(&mut out, &compress(f));
+    }
+    out
+}
+
+/// Decodes the output of `encode_frames` back into its frames.
+///
+/// Errors: as `decompress`, plus
+/// - TruncatedFrame  (a length prefix or a frame runs past the end of `data`)
+pub fn decode_frames(data: &[u8]) -> Result<Vec<Vec<u8>>, Error> {
+    split_frames(data)?.into_iter().map(decompress).collect()
+}
+
+/// Appends `n` as unsigned LEB128.
+pub(crate) fn put_varint(out: &mut Vec<u8>, mut n: usize) {
+    while n >= 0x80 {
+        out.push(n as u8 | 0x80);
+        n >>= 7;
+    }
+    out.push(n as u8);
+}
+
+/// Appends one frame.
+pub(crate) fn put_frame(out: &mut Vec<u8>, frame: &[u8]) {
+    put_varint(out, frame.len());
+    out.extend_from_slice(frame);
+}
+
+/// Splits `data` into its frames.
+///
+/// Errors:
+/// - TruncatedFrame  (a length prefix or a frame runs past the end of `data`)
+pub(crate) fn split_frames(mut data: &[u8]) -> Result<Vec<&[u8]>, Error> {
+    let mut frames = Vec::new();
+    while !data.is_empty() {
+        let (len, rest) = get_varint(data)?;
+        if len > rest.len() {
+            return Err(Error::TruncatedFrame);
+        }
+        let (frame, rest) = rest.split_at(len);
+        frames.push(frame);
+        data = rest;
+    }
+    Ok(frames)
+}
+
+/// Reads an unsigned LEB128 length. A value too large for `usize` cannot fit
+/// in `data` either, so it is reported as a truncated frame.
+fn get_varint(data: &[u8]) -> Result<(usize, &[u8]), Error> {
+    let mut n = 0usize;
+    for (i, &b) in data.iter().enumerate() {
+        let shift = 7 * i as u32;
+        if shift >= usize::BITS || ((b & 0x7F) as usize) << shift >> shift != (b & 0x7F) as usize {
+            return Err(Error::TruncatedFrame);
+        }
+        n |= ((b & 0x7F) as usize) << shift;
+        if b & 0x80 == 0 {
+            return Ok((n, &data[i + 1..]));
+        }
+    }
+    Err(Error::TruncatedFrame)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test] fn frames_roundtrip() {
+        let big = [0u8; 20_000];
+        let frames: [&[u8]; 5] = [&[], &[1, 2, 3], &[0xFF; 40], &big, &[0, 0xFF, 0]];
+        let data = encode_frames(&frames);
+        assert_eq!(decode_frames(&data).unwrap(), frames);
+        assert_eq!(decode_frames(&[]).unwrap(), Vec::<Vec<u8>>::new());
+        assert_eq!(decode_frames(&encode_frames(&[&[]])).unwrap(), [Vec::<u8>::new()]);
+    }
+    #[test] fn frames_truncated() {
+        let data = encode_frames(&[&[1, 2, 3], &[4, 5, 6]]);
+        for n in 1..data.len() {
+            if n == 4 { continue; } // a whole first frame is a valid container
+            assert_eq!(decode_frames(&data[..n]), Err(Error::TruncatedFrame), "cut at {}", n);
+        }
+        assert_eq!(decode_frames(&[0x05, 1, 2]), Err(Error::TruncatedFrame));
+    }
+
+    #[test] fn varint_roundtrip() {
+        for n in [0, 1, 0x7F, 0x80, 0x3FFF, 0x4000, usize::MAX] {
+            let mut v = Vec::new();
+            put_varint(&mut v, n);
+            assert_eq!(get_varint(&v), Ok((n, &[][..])), "{}", n);
+        }
+        assert_eq!(get_varint(&[0x80]), Err(Error::TruncatedFrame));
+        assert_eq!(get_varint(&[0xFF; 11]), Err(Error::TruncatedFrame));
+    }
+}
@@ -0,0 +1,720 @@
+//! `std::io` adapters.
+
+use std::fs::File;
+use std::io::{self, BufRead, BufWriter, Read, Write};
+use std::path::Path;
+
+use crate::decode::Decoder;
+use crate::encode::{Encoder, BLOCK_END};
+use crate::sink::{Negate, Sink};
+use crate::Error;
+
+/// Input is fed to the encoder/decoder in pieces of this size, so a write error
+/// stops the work after at most one piece.
+const CHUNK: usize = 8 * 1024;
+
+/// Decode errors surface as `io::ErrorKind::InvalidData` carrying the [`Error`];
+/// recover it with `io_err.get_ref().and_then(|e| e.downcast_ref::<cdrle::Error>())`.
+impl From<Error> for io::Error {
+    fn from(e: Error) -> Self { io::Error::new(io::ErrorKind::InvalidData, e) }
+}
+
+/// Compresses everything written to it into the inner writer `W`.
+///
+/// Completed runs and literals are passed downstream on every `write`; a run
+/// still open at the end of a `write` is held until more input or
+/// [`finish`](CompressWriter::finish). Call `finish` to emit the trailing run;
+/// dropping the writer without it loses that run.
+///
+/// Header negation needs no extra buffering: an emitted byte is never changed
+/// afterwards, so each of the first 4 stream bytes is negated as it is produced.
+///
+/// If the inner writer fails after input was encoded, that input still counts
+/// as written: the unsent bytes are kept and go out first on the next `write`,
+/// `flush`, `flush_block` or `finish`, which report the error if it persists.
+/// Retrying never duplicates input or re-negates the header.
+#[derive(Debug)]
+pub struct CompressWriter<W: Write> {
+    inner: W,
+    enc: Encoder,
+    buf: Vec<u8>, // encoded (and already negated) bytes not yet written downstream
+    head: usize,  // header bytes of the current block still to negate
+}
+
+impl<W: Write> CompressWriter<W> {
+    pub fn new(inner: W) -> Self {
+        Self { inner, enc: Encoder::default(), buf: Vec::new(), head: 4 }
+    }
+
+    pub fn get_ref(&self) -> &W { &self.inner }
+
+    /// Writing to the inner writer directly corrupts the compressed stream.
+    pub fn get_mut(&mut self) -> &mut W { &mut self.inner }
+
+    /// Emits the trailing run, flushes, and returns the inner writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.drain()?;
+        self.stage(|enc, out| enc.flush(out));
+        self.drain()?;
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+
+    /// Ends the current block, as [`Compressor::flush_block`](crate::Compressor::flush_block),
+    /// and writes it downstream. Later writes start a new, independent block.
+    pub fn flush_block(&mut self) -> io::Result<()> {
+        self.drain()?;
+        self.stage(|enc, out| {
+            enc.flush(out);
+            out.push_slice(&BLOCK_END);
+        });
+        self.head = 4;
+        self.drain_accepted();
+        Ok(())
+    }
+
+    /// Runs `f` on the encoder, appending its output to `buf` with the header
+    /// negated on the way in.
+    fn stage(&mut self, f: impl FnOnce(&mut Encoder, &mut Negate<'_, Vec<u8>>)) {
+        let mut out = Negate { inner: &mut self.buf, left: self.head };
+        f(&mut self.enc, &mut out);
+        self.head = out.left;
+    }
+
+    /// Writes `buf` downstream. On error the unsent tail stays in `buf`.
+    fn drain(&mut self) -> io::Result<()> {
+        let mut sent = 0;
+        let r = loop {
+            if sent == self.buf.len() { break Ok(()); }
+            match self.inner.write(&self.buf[sent..]) {
+                Ok(0) => break Err(io::ErrorKind::WriteZero.into()),
+                Ok(n) => sent += n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => break Err(e),
+            }
+        };
+        self.buf.drain(..sent);
+        r
+    }
+
+    /// `drain` after input has been accepted: an error is left for the next call.
+    fn drain_accepted(&mut self) { let _ = self.drain(); }
+}
+
+impl<W: Write> Write for CompressWriter<W> {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        self.drain()?;
+        self.stage(|enc, out| enc.push(data, out));
+        self.drain_accepted();
+        Ok(data.len())
+    }
+
+    /// Encodes the slices as one contiguous input, so runs spanning slice
+    /// boundaries stay whole, and passes the result downstream in one write.
+    fn write_vectored(&mut self, bufs: &[io::IoSlice<'_>]) -> io::Result<usize> {
+        self.drain()?;
+        let mut n = 0;
+        self.stage(|enc, out| {
+            for b in bufs {
+                enc.push(b, out);
+                n += b.len();
+            }
+        });
+        self.drain_accepted();
+        Ok(n)
+    }
+
+    /// Writes any bytes held back by an earlier error, then flushes the inner
+    /// writer. A pending run is *not* emitted, since that would change the
+    /// output; only `finish` ends the stream.
+    fn flush(&mut self) -> io::Result<()> {
+        self.drain()?;
+        self.inner.flush()
+    }
+}
+
+/// Decompresses the stream read from the inner reader `R`.
+///
+/// Compressed input is decoded straight into the caller's buffer, a little at
+/// a time so that a run straddling its end leaves at most one run (128 bytes)
+/// staged for the next `read`. Header negation follows the absolute stream
+/// position, however the inner reader splits its data.
+///
+/// On a malformed stream, the bytes decoded before the bad token are returned
+/// first; the error surfaces on the following `read`, and on every one after.
+#[derive(Debug)]
+pub struct DecompressReader<R: Read> {
+    inner: R,
+    buf: Vec<u8>, // compressed bytes read ahead from `inner`
+    start: usize,
+    end: usize,
+    dec: Decoder,
+    staged: Vec<u8>, // decoded bytes that did not fit the caller's buffer
+    taken: usize,    // of those, bytes already returned
+    err: Option<Error>,
+}
+
+impl<R: Read> DecompressReader<R> {
+    pub fn new(inner: R) -> Self { Self::with_capacity(8 * 1024, inner) }
+
+    /// Like `new`, but reads ahead from `inner` at most `cap` (at least 1) bytes at a time.
+    pub fn with_capacity(cap: usize, inner: R) -> Self {
+        Self {
+            inner,
+            buf: vec![0; cap.max(1)],
+            start: 0,
+            end: 0,
+            dec: Decoder::with_limit(usize::MAX),
+            staged: Vec::new(),
+            taken: 0,
+            err: None,
+        }
+    }
+
+    pub fn get_ref(&self) -> &R { &self.inner }
+
+    pub fn into_inner(self) -> R { self.inner }
+
+    /// Refills the read-ahead buffer; returns `false` at end of input.
+    fn refill(&mut self) -> io::Result<bool> {
+        let n = loop {
+            match self.inner.read(&mut self.buf) {
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                r => break r?,
+            }
+        };
+        self.start = 0;
+        self.end = n;
+        Ok(n != 0)
+    }
+}
+
+impl<R: Read> Read for DecompressReader<R> {
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        let k = (self.staged.len() - self.taken).min(out.len());
+        out[..k].copy_from_slice(&self.staged[self.taken..self.taken + k]);
+        self.taken += k;
+        if self.taken == self.staged.len() {
+            self.staged.clear();
+            self.taken = 0;
+        }
+        let mut n = k;
+        while n < out.len() {
+            if let Some(e) = self.err {
+                if n != 0 { break; }
+                return Err(e.into());
+            }
+            if self.start == self.end {
+                // Return what we have rather than block on the inner reader.
+                if n != 0 { break; }
+                if !self.refill()? {
+                    self.dec.finish()?;
+                    break;
+                }
+            }
+            // `room / 64` compressed bytes hold at most `room / 128` runs (plus
+            // one finishing a pending marker), so `out` overflows by at most one run.
+            let room = out.len() - n;
+            let end = self.end.min(self.start + (room / 64).max(1));
+            let mut sink = SplitSink { out: &mut out[n..], n: 0, rest: &mut self.staged };
+            let r = self.dec.push(&self.buf[self.start..end], &mut sink);
+            n += sink.n;
+            self.start = end;
+            self.err = r.err();
+        }
+        Ok(n)
+    }
+}
+
+/// Fills `out` from the front; what does not fit goes to `rest`.
+struct SplitSink<'a> {
+    out: &'a mut [u8],
+    n: usize,
+    rest: &'a mut Vec<u8>,
+}
+
+impl Sink for SplitSink<'_> {
+    #[inline]
+    fn push_byte(&mut self, b: u8) {
+        match self.out.get_mut(self.n) {
+            Some(slot) => { *slot = b; self.n += 1; }
+            None => self.rest.push(b),
+        }
+    }
+
+    #[inline]
+    fn push_fill(&mut self, b: u8, len: usize) {
+        let k = len.min(self.out.len() - self.n);
+        self.out[self.n..self.n + k].fill(b);
+        self.n += k;
+        self.rest.push_fill(b, len - k);
+    }
+}
+
+/// Push-based decompressor writing decoded bytes into the inner writer `W`.
+///
+/// Feed compressed chunks with [`push`](StreamingDecompressor::push), then call
+/// [`finish`](StreamingDecompressor::finish). Like [`Decompressor`](crate::Decompressor),
+/// a run marker may end one chunk and its CONTROL byte start the next; unlike
+/// it, the output is never held in memory beyond an 8 KiB staging buffer.
+#[derive(Debug)]
+pub struct StreamingDecompressor<W: Write> {
+    inner: W,
+    dec: Decoder,
+}
+
+impl<W: Write> StreamingDecompressor<W> {
+    /// A decompressor with no cap on the output size: `W` decides what to keep.
+    pub fn new(inner: W) -> Self { Self::with_limit(inner, usize::MAX) }
+
+    /// Fails with `OutputLimitExceeded` rather than write more than `limit` bytes.
+    pub fn with_limit(inner: W, limit: usize) -> Self {
+        Self { inner, dec: Decoder::with_limit(limit) }
+    }
+
+    pub fn get_ref(&self) -> &W { &self.inner }
+
+    /// Decodes `chunk` and writes the result to `W`. A trailing run marker
+    /// stays pending until the next `push`.
+    ///
+    /// Decode errors surface as `InvalidData` carrying the [`Error`], after `W`
+    /// has received the bytes decoded before it; the stream is then corrupt and
+    /// the decompressor should be discarded.
+    pub fn push(&mut self, chunk: &[u8]) -> io::Result<()> {
+        let mut sink = WriteSink::new(&mut self.inner);
+        for piece in chunk.chunks(CHUNK) {
+            if let Err(e) = self.dec.push(piece, &mut sink) {
+                sink.finish()?;
+                return Err(e.into());
+            }
+            if sink.err.is_some() { break; }
+        }
+        sink.finish()
+    }
+
+    /// Checks that the stream did not end on a dangling run marker, flushes,
+    /// and returns the inner writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.dec.finish()?;
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+}
+
+/// Compresses everything `r` yields until end of input, reading it in 8 KiB
+/// chunks through a [`Compressor`](crate::Compressor): runs spanning chunk
+/// boundaries stay whole and the header negation is applied once, so the result
+/// equals `compress` of the whole input. Retries reads that fail with `Interrupted`.
+pub fn compress_reader<R: Read>(r: &mut R) -> io::Result<Vec<u8>> {
+    let mut c = crate::Compressor::new();
+    let mut buf = [0u8; CHUNK];
+    loop {
+        match r.read(&mut buf) {
+            Ok(0) => return Ok(c.finish()),
+            Ok(n) => c.push(&buf[..n]),
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Decompresses everything `r` yields until end of input, decoding straight
+/// from the reader's own buffer (`fill_buf`/`consume`) with no copy in between.
+/// A run marker at the end of one filled buffer is completed by the first byte
+/// of the next. Retries reads that fail with `Interrupted`.
+///
+/// Decode errors surface as `InvalidData`, as for [`DecompressReader`]; output
+/// is capped at `DEFAULT_MAX_OUTPUT` like `decompress`.
+pub fn decompress_bufread<R: BufRead>(r: &mut R) -> io::Result<Vec<u8>> {
+    let mut out = Vec::new();
+    let mut dec = Decoder::default();
+    loop {
+        let buf = match r.fill_buf() {
+            Ok(buf) => buf,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        if buf.is_empty() {
+            dec.finish()?;
+            return Ok(out);
+        }
+        dec.push(buf, &mut out)?;
+        let n = buf.len();
+        r.consume(n);
+    }
+}
+
+/// Compresses the file `src` into `dst` (created or truncated), streaming
+/// through a [`CompressWriter`] so neither file has to fit in memory.
+///
+/// `src` is opened before `dst` is touched, so a missing or unreadable `src`
+/// leaves an existing `dst` intact; `src` and `dst` naming the same file fails
+/// with `io::ErrorKind::InvalidInput` instead of truncating the input.
+pub fn compress_file(src: &Path, dst: &Path) -> io::Result<()> {
+    let (mut r, w) = open_pair(src, dst)?;
+    let mut w = CompressWriter::new(w);
+    io::copy(&mut r, &mut w)?;
+    w.finish()?.into_inner().map_err(|e| e.into_error())?;
+    Ok(())
+}
+
+/// Decompresses the file `src` into `dst` (created or truncated), streaming
+/// through a [`DecompressReader`]. A malformed stream fails with
+/// `io::ErrorKind::InvalidData` carrying the [`Error`], after `dst` has received
+/// the bytes decoded before it. `src` and `dst` are opened as by [`compress_file`].
+pub fn decompress_file(src: &Path, dst: &Path) -> io::Result<()> {
+    let (r, mut w) = open_pair(src, dst)?;
+    io::copy(&mut DecompressReader::new(r), &mut w)?;
+    w.flush()
+}
+
+/// Opens `src`, then creates `dst`, refusing to truncate `src` itself.
+fn open_pair(src: &Path, dst: &Path) -> io::Result<(File, BufWriter<File>)> {
+    let r = File::open(src)?;
+    if let (Ok(a), Ok(b)) = (std::fs::canonicalize(src), std::fs::canonicalize(dst)) {
+        if a == b {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "cdrle: src and dst are the same file"));
+        }
+    }
+    Ok((r, BufWriter::new(File::create(dst)?)))
+}
+
+/// Stages output in a fixed buffer and writes it to `W` when full. The first
+/// write error is kept and everything after it dropped.
+struct WriteSink<'a, W: Write> {
+    w: &'a mut W,
+    buf: [u8; CHUNK],
+    len: usize,
+    err: Option<io::Error>,
+}
+
+impl<'a, W: Write> WriteSink<'a, W> {
+    fn new(w: &'a mut W) -> Self { Self { w, buf: [0; CHUNK], len: 0, err: None } }
+
+    fn drain(&mut self) {
+        if self.err.is_none() {
+            if let Err(e) = self.w.write_all(&self.buf[..self.len]) { self.err = Some(e); }
+        }
+        self.len = 0;
+    }
+
+    /// Writes out the staged bytes and reports the first error, if any.
+    fn finish(mut self) -> io::Result<()> {
+        self.drain();
+        self.err.map_or(Ok(()), Err)
+    }
+}
+
+impl<W: Write> Sink for WriteSink<'_, W> {
+    #[inline]
+    fn push_byte(&mut self, b: u8) {
+        if self.len == CHUNK { self.drain(); }
+        self.buf[self.len] = b;
+        self.len += 1;
+    }
+
+    fn push_fill(&mut self, b: u8, mut n: usize) {
+        while n != 0 {
+            if self.len == CHUNK { self.drain(); }
+            let k = n.min(CHUNK - self.len);
+            self.buf[self.len..self.len + k].fill(b);
+            self.len += k;
+            n -= k;
+        }
+    }
+}
+
+/// Compresses `input` straight into `w`, staging output in a fixed 8 KiB
+/// buffer instead of a `Vec` of the whole result. Produces exactly the bytes of
+/// `compress(input)`.
+///
+/// Nothing is buffered or back-patched for the header negation: an emitted byte
+/// never changes, so each of the first 4 is negated as it is produced. On a
+/// write error, `w` may have received part of the stream.
+pub fn compress_to_writer<W: Write>(input: &[u8], w: &mut W) -> io::Result<()> {
+    let mut sink = WriteSink::new(w);
+    let mut out = Negate { inner: &mut sink, left: 4 };
+    let mut enc = Encoder::default();
+    for chunk in input.chunks(CHUNK) {
+        enc.push(chunk, &mut out);
+        if out.inner.err.is_some() { break; }
+    }
+    enc.flush(&mut out);
+    sink.finish()
+}
+
+/// Decompresses `comp` straight into `w`, staging output in a fixed 8 KiB buffer.
+///
+/// Decode errors are returned as `io::ErrorKind::InvalidData` carrying the
+/// [`Error`]. Output is streamed, so on any error `w` may already hold the bytes
+/// decoded before it; the output size is not capped (`w` decides what to keep).
+pub fn decompress_to_writer<W: Write>(comp: &[u8], w: &mut W) -> io::Result<()> {
+    let mut sink = WriteSink::new(w);
+    let mut dec = Decoder::with_limit(usize::MAX);
+    for chunk in comp.chunks(CHUNK) {
+        if let Err(e) = dec.push(chunk, &mut sink) {
+            sink.finish()?;
+            return Err(e.into());
+        }
+        if sink.err.is_some() { break; }
+    }
+    sink.finish()?;
+    Ok(dec.finish()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{compress, decompress};
+
+    #[test] fn writer_matches_compress() {
+        let mut v = vec![0u8; 300];
+        v.extend_from_slice(&[0xFF; 70]);
+        v.extend_from_slice(&[0,0,0,0, 42, 0xFF,0xFF,0xFF, 1,2,3, 0, 0xFF, 0, 0xAA,0xBB, 0, 0xFF]);
+        for n in [1, 2, 3, 5, 32, 129, v.len()] {
+            let mut w = CompressWriter::new(Vec::new());
+            for chunk in v.chunks(n) { w.write_all(chunk).unwrap(); }
+            let c = w.finish().unwrap();
+            assert_eq!(c, compress(&v), "chunk size {}", n);
+            assert_eq!(decompress(&c).unwrap(), v);
+        }
+    }
+    /// Yields one byte per `read` call.
+    struct Trickle<'a>(&'a [u8]);
+    impl Read for Trickle<'_> {
+        fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+            if self.0.is_empty() || out.is_empty() { return Ok(0); }
+            out[0] = self.0[0];
+            self.0 = &self.0[1..];
+            Ok(1)
+        }
+    }
+
+    #[test] fn reader_matches_decompress() {
+        let mut v = vec![0u8; 300];
+        v.extend_from_slice(&[0xFF; 70]);
+        v.extend_from_slice(&[0,0,0,0, 42, 0xFF,0xFF,0xFF, 1,2,3, 0, 0xFF, 0, 0xAA,0xBB, 0, 0xFF]);
+        let c = compress(&v);
+        let mut out = Vec::new();
+        DecompressReader::new(&c[..]).read_to_end(&mut out).unwrap();
+        assert_eq!(out, v);
+        out.clear();
+        DecompressReader::with_capacity(1, Trickle(&c)).read_to_end(&mut out).unwrap();
+        assert_eq!(out, v);
+        // Small caller buffers split runs across `read` calls.
+        let mut r = DecompressReader::new(&c[..]);
+        let mut small = [0u8; 7];
+        out.clear();
+        loop {
+            let n = r.read(&mut small).unwrap();
+            if n == 0 { break; }
+            out.extend_from_slice(&small[..n]);
+        }
+        assert_eq!(out, v);
+    }
+    #[test] fn reader_errors() {
+        let mut out = Vec::new();
+        let e = DecompressReader::new(Trickle(&[1 ^ 0xFF, 0xFF])).read_to_end(&mut out).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(e.get_ref().and_then(|e| e.downcast_ref::<Error>()), Some(&Error::RunMarkerWithoutControl));
+        let e = DecompressReader::new(&[0xFF, 0xA0 ^ 0xFF][..]).read_to_end(&mut out).unwrap_err();
+        assert_eq!(e.get_ref().and_then(|e| e.downcast_ref::<Error>()), Some(&Error::InvalidRunLength { len: 33 }));
+    }
+    #[test] fn reader_returns_bytes_before_error() {
+        let c = [1 ^ 0xFF, 2 ^ 0xFF, 0xFF, 0xFF ^ 0xFF]; // literals, then an 0xFF-run of 128
+        let mut r = DecompressReader::new(&c[..]);
+        let mut out = [0u8; 16];
+        assert_eq!(r.read(&mut out).unwrap(), 2);
+        assert_eq!(out[..2], [1, 2]);
+        for _ in 0..2 {
+            let e = r.read(&mut out).unwrap_err();
+            assert_eq!(e.get_ref().and_then(|e| e.downcast_ref::<Error>()), Some(&Error::InvalidRunLength { len: 128 }));
+        }
+        // A run straddling the caller's buffer is staged, not lost.
+        let v = [&[5u8][..], &[0; 128], &[6], &[0xFF; 32], &[7]].concat();
+        let mut c = compress(&v);
+        c.extend_from_slice(&[0x00, 0xFF]);
+        let mut r = DecompressReader::new(&c[..]);
+        let mut got = Vec::new();
+        let mut small = [0u8; 10];
+        let e = loop {
+            match r.read(&mut small) {
+                Ok(n) => got.extend_from_slice(&small[..n]),
+                Err(e) => break e,
+            }
+        };
+        assert_eq!(got, v);
+        assert_eq!(e.get_ref().and_then(|e| e.downcast_ref::<Error>()), Some(&Error::InvalidRunLength { len: 128 }));
+    }
+    #[test] fn writer_vectored_keeps_runs_across_slices() {
+        let (a, b) = ([7, 0, 0, 0], [0, 0, 0xFF, 0xFF]);
+        let mut w = CompressWriter::new(Vec::new());
+        let n = w.write_vectored(&[io::IoSlice::new(&a), io::IoSlice::new(&[]), io::IoSlice::new(&b)]).unwrap();
+        assert_eq!(n, 8);
+        let c = w.finish().unwrap();
+        assert_eq!(c, compress(&[7, 0, 0, 0, 0, 0, 0xFF, 0xFF]));
+        assert_eq!(c.len(), 5); // one zero run, not two
+    }
+    #[test] fn writer_blocks_match_compressor() {
+        let mut w = CompressWriter::new(Vec::new());
+        let mut c = crate::Compressor::new();
+        for r in [&[0u8, 0, 1][..], &[], &[0xFF, 2, 3, 4, 5, 0]] {
+            w.write_all(r).unwrap();
+            w.flush_block().unwrap();
+            c.push(r);
+            c.flush_block();
+        }
+        w.write_all(&[7, 0]).unwrap();
+        c.push(&[7, 0]);
+        assert_eq!(w.finish().unwrap(), c.finish());
+    }
+    #[test] fn compress_reader_matches_compress() {
+        let mut v = vec![0u8; 300];
+        v.extend_from_slice(&[0xFF; 70]);
+        v.extend_from_slice(&[0,0,0,0, 42, 0xFF,0xFF,0xFF, 1,2,3, 0, 0xFF, 0, 0xAA,0xBB, 0, 0xFF]);
+        assert_eq!(compress_reader(&mut Trickle(&v)).unwrap(), compress(&v)); // 1-byte reads
+        assert_eq!(compress_reader(&mut &v[..]).unwrap(), compress(&v));
+        assert_eq!(compress_reader(&mut &[][..]).unwrap(), compress(&[]));
+        let big = vec![0u8; 3 * CHUNK + 1];
+        assert_eq!(compress_reader(&mut &big[..]).unwrap(), compress(&big));
+    }
+    #[test] fn bufread_splits_markers_across_fills() {
+        let mut v = vec![0u8; 300];
+        v.extend_from_slice(&[0xFF; 70]);
+        v.extend_from_slice(&[0,0,0,0, 42, 0xFF,0xFF,0xFF, 1,2,3, 0, 0xFF, 0, 0xAA,0xBB, 0, 0xFF]);
+        let c = compress(&v);
+        for cap in [1, 2, 3, 7, c.len()] {
+            let mut r = io::BufReader::with_capacity(cap, &c[..]);
+            assert_eq!(decompress_bufread(&mut r).unwrap(), v, "capacity {}", cap);
+        }
+        assert_eq!(decompress_bufread(&mut &[][..]).unwrap(), []);
+        let e = decompress_bufread(&mut io::BufReader::with_capacity(1, &[1 ^ 0xFF, 0xFF][..])).unwrap_err();
+        assert_eq!(e.get_ref().and_then(|e| e.downcast_ref::<Error>()), Some(&Error::RunMarkerWithoutControl));
+        let e = decompress_bufread(&mut io::BufReader::with_capacity(1, &[0xFF, 0xA0 ^ 0xFF][..])).unwrap_err();
+        assert_eq!(e.get_ref().and_then(|e| e.downcast_ref::<Error>()), Some(&Error::InvalidRunLength { len: 33 }));
+    }
+    #[test] fn streaming_decompressor_writes_incrementally() {
+        let mut v = vec![0u8; 20_000];
+        v.extend_from_slice(&[0,0,0,0, 42, 0xFF,0xFF,0xFF, 1,2,3, 0, 0xFF, 0, 0xAA,0xBB, 0, 0xFF]);
+        let c = compress(&v);
+        for n in [1, 2, 3, 7, c.len()] {
+            let mut d = StreamingDecompressor::new(Vec::new());
+            for chunk in c.chunks(n) { d.push(chunk).unwrap(); }
+            assert_eq!(d.finish().unwrap(), v, "chunk size {}", n);
+        }
+        let mut d = StreamingDecompressor::new(Vec::new());
+        d.push(&c[..1]).unwrap();
+        assert_eq!(d.get_ref().len(), 0); // marker pending
+        d.push(&c[1..2]).unwrap();
+        assert_eq!(d.get_ref().len(), 128); // written as soon as decoded
+
+        let mut d = StreamingDecompressor::new(Vec::new());
+        d.push(&[1 ^ 0xFF, 0xFF]).unwrap();
+        let e = d.finish().unwrap_err();
+        assert_eq!(e.get_ref().and_then(|e| e.downcast_ref::<Error>()), Some(&Error::RunMarkerWithoutControl));
+        let mut d = StreamingDecompressor::with_limit(Vec::new(), 1000);
+        let e = d.push(&c).unwrap_err();
+        assert_eq!(e.get_ref().and_then(|e| e.downcast_ref::<Error>()), Some(&Error::OutputLimitExceeded { limit: 1000 }));
+        assert_eq!(d.get_ref().len(), 896); // the runs that fit
+    }
+    #[test] fn file_helpers_roundtrip() {
+        let dir = std::env::temp_dir();
+        let tag = format!("cdrle-{}-{:?}", std::process::id(), std::thread::current().id());
+        let (raw, comp, back) = (dir.join(format!("{}.raw", tag)), dir.join(format!("{}.rle", tag)), dir.join(format!("{}.out", tag)));
+        let mut v = vec![0u8; 100_000];
+        v.extend((0..50_000u32).map(|i| (i % 7) as u8 | 0x80));
+        std::fs::write(&raw, &v).unwrap();
+        compress_file(&raw, &comp).unwrap();
+        assert_eq!(std::fs::read(&comp).unwrap(), compress(&v));
+        decompress_file(&comp, &back).unwrap();
+        assert_eq!(std::fs::read(&back).unwrap(), v);
+
+        std::fs::write(&comp, [1 ^ 0xFF, 0xFF]).unwrap();
+        let e = decompress_file(&comp, &back).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(e.to_string(), Error::RunMarkerWithoutControl.to_string());
+        // A missing src must not truncate an existing dst.
+        std::fs::write(&back, b"keep me").unwrap();
+        let missing = dir.join(format!("{}.missing", tag));
+        assert_eq!(compress_file(&missing, &back).unwrap_err().kind(), io::ErrorKind::NotFound);
+        assert_eq!(decompress_file(&missing, &back).unwrap_err().kind(), io::ErrorKind::NotFound);
+        assert_eq!(std::fs::read(&back).unwrap(), b"keep me");
+        // Nor may src == dst wipe the input.
+        assert_eq!(compress_file(&raw, &raw).unwrap_err().kind(), io::ErrorKind::InvalidInput);
+        assert_eq!(decompress_file(&raw, &raw).unwrap_err().kind(), io::ErrorKind::InvalidInput);
+        assert_eq!(std::fs::read(&raw).unwrap(), v);
+        for p in [raw, comp, back] { let _ = std::fs::remove_file(p); }
+    }
+    /// Fails the `n`th `write` call (counting from 0) once, after accepting part of it.
+    struct FailOnce { out: Vec<u8>, calls: usize, n: usize }
+    impl Write for FailOnce {
+        fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+            self.calls += 1;
+            if self.calls - 1 == self.n {
+                return Err(io::Error::new(io::ErrorKind::Other, "transient"));
+            }
+            let k = data.len().min(3); // short writes, so errors land mid-buffer
+            self.out.extend_from_slice(&data[..k]);
+            Ok(k)
+        }
+        fn flush(&mut self) -> io::Result<()> { Ok(()) }
+    }
+
+    #[test] fn writer_survives_downstream_error() {
+        let v: Vec<u8> = [&[1u8, 2, 0, 0, 0xFF][..], &[9; 20], &[0; 300], &[0, 5, 0xFF, 6]].concat();
+        let mut c = crate::Compressor::new();
+        c.push(&v[..7]);
+        c.flush_block();
+        c.push(&v[7..]);
+        let expected = c.finish();
+        for n in 0..12 {
+            let mut w = CompressWriter::new(FailOnce { out: Vec::new(), calls: 0, n });
+            for (i, chunk) in [&v[..3], &v[3..7], &v[7..40], &v[40..]].into_iter().enumerate() {
+                // A caller retrying until its input is accepted.
+                let k = loop { if let Ok(k) = w.write(chunk) { break k; } };
+                assert_eq!(k, chunk.len());
+                if i == 1 { while w.flush_block().is_err() {} }
+            }
+            assert!(w.get_ref().calls > n, "write #{} never reached", n);
+            assert_eq!(w.finish().unwrap().out, expected, "failing write #{}", n);
+        }
+        let mut w = CompressWriter::new(FailOnce { out: Vec::new(), calls: 0, n: 0 });
+        assert_eq!(w.write_vectored(&[io::IoSlice::new(&[1, 2, 3, 4, 5])]).unwrap(), 5); // accepted
+        w.flush().unwrap(); // resends what the error held back
+        assert_eq!(w.finish().unwrap().out, compress(&[1, 2, 3, 4, 5]));
+    }
+    #[test] fn writer_short_streams() {
+        for v in [&[][..], &[1], &[0], &[1, 0xFF], &[1, 2, 3, 4, 5]] {
+            let mut w = CompressWriter::new(Vec::new());
+            for &b in v { w.write_all(&[b]).unwrap(); }
+            assert_eq!(w.finish().unwrap(), compress(v));
+        }
+    }
+    #[test] fn to_writer_matches_one_shot() {
+        let mut v = vec![0u8; 20_000];
+        v.extend_from_slice(&[0xFF; 70]);
+        v.extend_from_slice(&[0,0,0,0, 42, 0xFF,0xFF,0xFF, 1,2,3, 0, 0xFF, 0, 0xAA,0xBB, 0, 0xFF]);
+        v.extend((0..30_000u32).map(|i| (i % 251) as u8));
+        for v in [&v[..], &[], &[0], &[1, 2]] {
+            let mut c = Vec::new();
+            compress_to_writer(v, &mut c).unwrap();
+            assert_eq!(c, compress(v));
+            let mut d = Vec::new();
+            decompress_to_writer(&c, &mut d).unwrap();
+            assert_eq!(d, v);
+        }
+    }
+    #[test] fn to_writer_errors() {
+        let mut d = Vec::new();
+        let e = decompress_to_writer(&[1 ^ 0xFF, 2 ^ 0xFF, 0xFF], &mut d).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(e.get_ref().and_then(|e| e.downcast_ref::<Error>()), Some(&Error::RunMarkerWithoutControl));
+        assert_eq!(d, [1, 2]);
+
+        let mut full = [0u8; 3];
+        let e = compress_to_writer(&[7; 100_000], &mut &mut full[..]).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::WriteZero);
+        let e = decompress_to_writer(&compress(&[0; 100_000]), &mut &mut full[..]).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::WriteZero);
+    }
+}
@@ -0,0 +1,305 @@
+//! Lazy, allocation-free iterators over compressed streams.
+
+use core::iter::FusedIterator;
+
+use crate::scan::run_len;
+use crate::{Error, MAX_FF_RUN, MAX_ZERO_RUN};
+
+/// Iterator over the decompressed bytes of a stream; see [`decompress_iter`].
+#[derive(Clone, Debug)]
+pub struct DecompressIter<'a> {
+    tokens: DecodeTokens<'a>,
+    fill: u8,   // byte of the run being expanded
+    run: usize, // bytes of that run still to yield
+}
+
+/// Iterates the decompressed bytes of `comp` without materializing them.
+///
+/// Runs are expanded on the fly, so each `next()` is O(1). A malformed stream
+/// yields one `Err` item, then `None`.
+pub fn decompress_iter(comp: &[u8]) -> DecompressIter<'_> {
+    DecompressIter { tokens: decode_tokens(comp), fill: 0, run: 0 }
+}
+
+impl Iterator for DecompressIter<'_> {
+    type Item = Result<u8, Error>;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.run != 0 {
+            self.run -= 1;
+            return Some(Ok(self.fill));
+        }
+        let (fill, len) = match self.tokens.next()? {
+            Ok(Token::Literal(b)) => return Some(Ok(b)),
+            Ok(Token::ZeroRun(len)) => (0x00, len),
+            Ok(Token::FfRun(len)) => (0xFF, len),
+            Err(e) => return Some(Err(e)),
+        };
+        self.fill = fill;
+        self.run = len - 1;
+        Some(Ok(fill))
+    }
+}
+
+impl FusedIterator for DecompressIter<'_> {}
+
+/// One segment of the input as `compress` encodes it; see [`runs`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Run<'a> {
+    /// A 0x00 run, `1..=MAX_ZERO_RUN` long: one `(0x00, CONTROL)` pair.
+    Zeros(usize),
+    /// A 0xFF run, `1..=MAX_FF_RUN` long: one `(0x00, CONTROL)` pair.
+    Ffs(usize),
+    /// A maximal stretch of literal bytes (none of them 0x00 or 0xFF), copied as is.
+    Literal(&'a [u8]),
+}
+
+/// Iterator over the segmentation of an input; see [`runs`].
+#[derive(Clone, Debug)]
+pub struct Runs<'a> {
+    input: &'a [u8],
+    i: usize,
+    fill: u8,   // byte of the run being split
+    run: usize, // bytes of that run not yet yielded
+}
+
+/// Iterates the segments `compress` would encode `input` as: literal stretches
+/// and runs, with long runs split at `MAX_ZERO_RUN`/`MAX_FF_RUN` exactly as the
+/// encoder splits them. Concatenating the segments reproduces `input`.
+pub fn runs(input: &[u8]) -> Runs<'_> {
+    Runs { input, i: 0, fill: 0, run: 0 }
+}
+
+impl<'a> Iterator for Runs<'a> {
+    type Item = Run<'a>;
+
+    fn next(&mut self) -> Option<Run<'a>> {
+        if self.run == 0 {
+            let b = *self.input.get(self.i)?;
+            if b != 0x00 && b != 0xFF {
+                let rest = &self.input[self.i..];
+                let n = rest.iter().position(|&b| b == 0x00 || b == 0xFF).unwrap_or(rest.len());
+                self.i += n;
+                return Some(Run::Literal(&rest[..n]));
+            }
+            self.fill = b;
+            self.run = run_len(&self.input[self.i..], b);
+            self.i += self.run;
+        }
+        let max = if self.fill == 0x00 { MAX_ZERO_RUN } else { MAX_FF_RUN };
+        let k = self.run.min(max);
+        self.run -= k;
+        Some(if self.fill == 0x00 { Run::Zeros(k) } else { Run::Ffs(k) })
+    }
+}
+
+impl FusedIterator for Runs<'_> {}
+
+/// Iterator over the compressed bytes of an input; see [`compress_iter`].
+#[derive(Clone, Debug)]
+pub struct CompressIter<'a> {
+    runs: Runs<'a>,
+    lit: &'a [u8],    // rest of the literal stretch being copied
+    ctrl: Option<u8>, // CONTROL byte owed after a run marker
+    pos: usize,       // compressed bytes yielded so far
+}
+
+/// Iterates the bytes `compress(input)` would return without materializing
+/// them, negating the first 4 as it goes; for piping into a hasher or socket.
+pub fn compress_iter(input: &[u8]) -> CompressIter<'_> {
+    CompressIter { runs: runs(input), lit: &[], ctrl: None, pos: 0 }
+}
+
+impl Iterator for CompressIter<'_> {
+    type Item = u8;
+
+    fn next(&mut self) -> Option<u8> {
+        let b = if let Some((&b, rest)) = self.lit.split_first() {
+            self.lit = rest;
+            b
+        } else if let Some(c) = self.ctrl.take() {
+            c
+        } else {
+            match self.runs.next()? {
+                Run::Literal(s) => {
+                    self.lit = &s[1..]; // never empty
+                    s[0]
+                }
+                Run::Zeros(n) => {
+                    self.ctrl = Some((n - 1) as u8);
+                    0x00
+                }
+                Run::Ffs(n) => {
+                    self.ctrl = Some(0x80 | (n - 1) as u8);
+                    0x00
+                }
+            }
+        };
+        let b = if self.pos < 4 { b ^ 0xFF } else { b };
+        self.pos += 1;
+        Some(b)
+    }
+}
+
+impl FusedIterator for CompressIter<'_> {}
+
+/// One decoded token of a compressed stream; see [`decode_tokens`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Token {
+    /// A literal byte (already un-negated).
+    Literal(u8),
+    /// A `(0x00, CONTROL)` pair expanding to this many 0x00 bytes.
+    ZeroRun(usize),
+    /// A `(0x00, CONTROL)` pair expanding to this many 0xFF bytes.
+    FfRun(usize),
+}
+
+/// Iterator over the tokens of a compressed stream; see [`decode_tokens`].
+#[derive(Clone, Debug)]
+pub struct DecodeTokens<'a> {
+    comp: &'a [u8],
+    i: usize,
+    failed: bool,
+}
+
+/// Iterates the tokens of `comp` without expanding runs, un-negating the first
+/// 4 bytes. Each token is 1 (literal) or 2 (run) compressed bytes, so offsets on
+/// both sides can be tallied as you go. A malformed stream yields one `Err`
+/// item, then `None`.
+pub fn decode_tokens(comp: &[u8]) -> DecodeTokens<'_> {
+    DecodeTokens::resume(comp, 0)
+}
+
+impl<'a> DecodeTokens<'a> {
+    /// Starts at compressed offset `i`, which must be a token boundary. The
+    /// header negation follows the absolute position, so any boundary works.
+    pub(crate) fn resume(comp: &'a [u8], i: usize) -> Self {
+        Self { comp, i, failed: false }
+    }
+
+    /// Compressed offset of the next token.
+    pub(crate) fn offset(&self) -> usize { self.i }
+
+    /// Compressed bytes not yet read.
+    #[cfg(feature = "alloc")]
+    pub(crate) fn remaining(&self) -> usize { self.comp.len().saturating_sub(self.i) }
+
+    #[inline]
+    fn read_unneg(&mut self) -> u8 {
+        let mut b = self.comp[self.i];
+        if self.i < 4 { b ^= 0xFF; }
+        self.i += 1;
+        b
+    }
+}
+
+impl Iterator for DecodeTokens<'_> {
+    type Item = Result<Token, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed || self.i >= self.comp.len() {
+            return None;
+        }
+        let b = self.read_unneg();
+        if b != 0x00 {
+            return Some(Ok(Token::Literal(b)));
+        }
+        if self.i >= self.comp.len() {
+            self.failed = true;
+            return Some(Err(Error::RunMarkerWithoutControl));
+        }
+        let c = self.read_unneg();
+        let len = (c & 0x7F) as usize + 1;
+        if c & 0x80 == 0 {
+            return Some(Ok(Token::ZeroRun(len)));
+        }
+        if len > MAX_FF_RUN {
+            self.failed = true;
+            return Some(Err(Error::InvalidRunLength { len }));
+        }
+        Some(Ok(Token::FfRun(len)))
+    }
+}
+
+impl FusedIterator for DecodeTokens<'_> {}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use super::*;
+    use crate::{compress, decompress};
+    use alloc::vec::Vec;
+
+    #[test] fn iter_matches_decompress() {
+        let mut v = alloc::vec![0u8; 300];
+        v.extend_from_slice(&[0xFF; 70]);
+        v.extend_from_slice(&[0,0,0,0, 42, 0xFF,0xFF,0xFF, 1,2,3, 0, 0xFF, 0, 0xAA,0xBB, 0, 0xFF]);
+        for v in [&v[..], &[], &[7], &[0]] {
+            let c = compress(v);
+            assert_eq!(decompress_iter(&c).collect::<Result<Vec<_>, _>>(), decompress(&c));
+        }
+    }
+    #[test] fn runs_reproduce_input() {
+        let v = [0,0,0,0, 42, 0xFF,0xFF,0xFF, 1,2,3, 0, 0xFF, 0, 0xAA,0xBB, 0, 0xFF];
+        let segs: Vec<_> = runs(&v).collect();
+        assert_eq!(segs, [
+            Run::Zeros(4), Run::Literal(&[42]), Run::Ffs(3), Run::Literal(&[1, 2, 3]), Run::Zeros(1),
+            Run::Ffs(1), Run::Zeros(1), Run::Literal(&[0xAA, 0xBB]), Run::Zeros(1), Run::Ffs(1),
+        ]);
+        let mut long = alloc::vec![0u8; 300];
+        long.extend_from_slice(&[0xFF; 40]);
+        long.push(5);
+        let segs: Vec<_> = runs(&long).collect();
+        assert_eq!(segs, [Run::Zeros(128), Run::Zeros(128), Run::Zeros(44), Run::Ffs(32), Run::Ffs(8), Run::Literal(&[5])]);
+        for v in [&v[..], &long, &[]] {
+            let mut cat = Vec::new();
+            for r in runs(v) {
+                match r {
+                    Run::Zeros(n) => cat.resize(cat.len() + n, 0x00),
+                    Run::Ffs(n) => cat.resize(cat.len() + n, 0xFF),
+                    Run::Literal(s) => cat.extend_from_slice(s),
+                }
+            }
+            assert_eq!(cat, v);
+        }
+    }
+    #[test] fn iter_error_then_none() {
+        let mut it = decompress_iter(&[1 ^ 0xFF, 0xFF]);
+        assert_eq!(it.next(), Some(Ok(1)));
+        assert_eq!(it.next(), Some(Err(Error::RunMarkerWithoutControl)));
+        assert_eq!(it.next(), None);
+        let c = [0xFF, 0xA0 ^ 0xFF, 0xFF];
+        assert_eq!(decompress_iter(&c).collect::<Result<Vec<_>, _>>(), decompress(&c));
+        assert_eq!(decompress_iter(&c).count(), 1);
+    }
+    #[test] fn compress_iter_matches_compress() {
+        let mut v = alloc::vec![0u8; 300];
+        v.extend_from_slice(&[0xFF; 70]);
+        v.extend_from_slice(&[0,0,0,0, 42, 0xFF,0xFF,0xFF, 1,2,3, 0, 0xFF, 0, 0xAA,0xBB, 0, 0xFF]);
+        for v in [&v[..], &[], &[7], &[0], &[1, 2, 3, 4, 5], &v[300..]] {
+            assert_eq!(compress_iter(v).collect::<Vec<_>>(), compress(v), "{:x?}", v);
+        }
+        let mut it = compress_iter(&[0]);
+        assert_eq!((it.next(), it.next(), it.next(), it.next()), (Some(0xFF), Some(0xFF), None, None));
+    }
+    #[test] fn tokens_sum_to_decompressed_len() {
+        let v = [0,0,0,0, 42, 0xFF,0xFF,0xFF, 1,2,3, 0, 0xFF, 0, 0xAA,0xBB, 0, 0xFF];
+        let toks: Vec<_> = decode_tokens(&compress(&v)).collect::<Result<_, _>>().unwrap();
+        assert_eq!(&toks[..3], [Token::ZeroRun(4), Token::Literal(42), Token::FfRun(3)]);
+        let mut long = alloc::vec![0u8; 300];
+        long.extend_from_slice(&[0xFF; 40]);
+        for v in [&v[..], &long, &[], &[9]] {
+            let c = compress(v);
+            let total: usize = decode_tokens(&c).map(|t| match t.unwrap() {
+                Token::Literal(_) => 1,
+                Token::ZeroRun(n) | Token::FfRun(n) => n,
+            }).sum();
+            assert_eq!(Ok(total), crate::decompressed_len(&c));
+        }
+        let mut it = decode_tokens(&[1 ^ 0xFF, 0xFF, 0xA0 ^ 0xFF, 5]);
+        assert_eq!(it.next(), Some(Ok(Token::Literal(1))));
+        assert_eq!(it.next(), Some(Err(Error::InvalidRunLength { len: 33 })));
+        assert_eq!(it.next(), None);
+        assert_eq!(decode_tokens(&[0xFF]).collect::<Vec<_>>(), [Err(Error::RunMarkerWithoutControl)]);
+    }
+}
@@ -0,0 +1,77 @@
+//! Length-prefixed raw RLE: `u32_le(decoded_len) ++ rle(body)`, for codecs
+//! that carry the uncompressed length up front instead of negating a header.
+//!
+//! The body is the `negate_header(false)` format (see `compress_plain`); no
+//! byte is negated, so this is *not* a `decompress` stream.
+
+use alloc::vec::Vec;
+
+use crate::decode::Decoder;
+use crate::encode::Encoder;
+use crate::sink::Counter;
+use crate::Error;
+
+/// Compresses `input` as a little-endian `u32` length followed by plain RLE.
+/// Decode with `decompress_lenprefixed`.
+///
+/// Panics if `input.len()` does not fit in a `u32`.
+pub fn compress_lenprefixed(input: &[u8]) -> Vec<u8> {
+    let len = u32::try_from(input.len()).expect("input longer than u32::MAX bytes");
+    let mut out = Vec::with_capacity(4 + input.len());
+    out.extend_from_slice(&len.to_le_bytes());
+    let mut enc = Encoder::default();
+    enc.push(input, &mut out);
+    enc.flush(&mut out);
+    out
+}
+
+/// Decodes the output of `compress_lenprefixed`. The body is validated and
+/// its decoded length checked against the header before anything is
+/// allocated, so a lying header cannot cause a large allocation.
+///
+/// Errors: as `decompress`, plus
+/// - TruncatedFrame                    (fewer than 4 bytes, no room for the header)
+/// - LengthMismatch{expected, actual}  (the body decodes to another length than the header)
+pub fn decompress_lenprefixed(data: &[u8]) -> Result<Vec<u8>, Error> {
+    if data.len() < 4 {
+        return Err(Error::TruncatedFrame);
+    }
+    let (head, body) = data.split_at(4);
+    let expected = u32::from_le_bytes([head[0], head[1], head[2], head[3]]) as usize;
+    let mut n = Counter::default();
+    let mut dec = Decoder::with_limit(usize::MAX).negating(0);
+    dec.push(body, &mut n)?;
+    dec.finish()?;
+    if n.0 != expected {
+        return Err(Error::LengthMismatch { expected, actual: n.0 });
+    }
+    let mut out = Vec::with_capacity(expected);
+    Decoder::with_limit(expected).negating(0).push(body, &mut out)?;
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compress_plain;
+
+    #[test] fn lenprefixed_roundtrip() {
+        let mut v = alloc::vec![0u8; 300];
+        v.extend_from_slice(&[0xFF; 70]);
+        v.extend_from_slice(&[0,0,0,0, 42, 0xFF,0xFF,0xFF, 1,2,3, 0, 0xFF, 0, 0xAA,0xBB, 0, 0xFF]);
+        for v in [&v[..], &[], &[7], &[0]] {
+            let c = compress_lenprefixed(v);
+            assert_eq!(c[..4], (v.len() as u32).to_le_bytes());
+            assert_eq!(c[4..], compress_plain(v));
+            assert_eq!(decompress_lenprefixed(&c).unwrap(), v);
+        }
+        assert_eq!(compress_lenprefixed(&[0, 0, 1]), [3, 0, 0, 0, 0x00, 0x01, 0x01]);
+    }
+    #[test] fn lenprefixed_errors() {
+        assert_eq!(decompress_lenprefixed(&[3, 0, 0]), Err(Error::TruncatedFrame));
+        assert_eq!(decompress_lenprefixed(&[3, 0, 0, 0, 1, 2]), Err(Error::LengthMismatch { expected: 3, actual: 2 }));
+        assert_eq!(decompress_lenprefixed(&[0xFF, 0xFF, 0xFF, 0xFF, 0x00, 0x7F]), Err(Error::LengthMismatch { expected: u32::MAX as usize, actual: 128 }));
+        assert_eq!(decompress_lenprefixed(&[1, 0, 0, 0, 0x00]), Err(Error::RunMarkerWithoutControl));
+        assert_eq!(decompress_lenprefixed(&[33, 0, 0, 0, 0x00, 0xA0]), Err(Error::InvalidRunLength { len: 33 }));
+    }
+}
@@ -1,114 +1,880 @@
 #![cfg_attr(not(feature = "std"), no_std)]
 #![cfg_attr(docsrs, feature(doc_cfg))]
+#![cfg_attr(cdrle_allocator_api, feature(allocator_api))]
+#[cfg(feature = "alloc")]
 extern crate alloc;
 
+#[cfg(feature = "alloc")]
+use alloc::borrow::Cow;
+#[cfg(feature = "alloc")]
 use alloc::vec::Vec;
 
+#[cfg(cdrle_allocator_api)]
+mod allocator;
+#[cfg(feature = "crc")]
+mod checked;
+mod chunks;
+#[cfg(feature = "alloc")]
+mod codec;
+mod compile_time;
+mod decode;
+mod encode;
+#[cfg(feature = "alloc")]
+mod frame;
+#[cfg(feature = "std")]
+mod io;
+mod iter;
+#[cfg(feature = "alloc")]
+mod lenprefixed;
+mod options;
+#[cfg(feature = "alloc")]
+mod packed;
+#[cfg(feature = "parallel")]
+mod parallel;
+#[cfg(feature = "alloc")]
+mod progress;
+mod scan;
+#[cfg(feature = "alloc")]
+mod seek;
+mod sink;
+#[cfg(feature = "alloc")]
+mod stats;
+#[cfg(feature = "alloc")]
+mod stream;
+#[cfg(feature = "alloc")]
+mod tagged;
+#[cfg(feature = "alloc")]
+mod typed;
+#[cfg(feature = "alloc")]
+mod v2;
+
+#[cfg(feature = "alloc")]
+use encode::{negate_header, negate_prefix};
+#[cfg(feature = "alloc")]
+use sink::InPlace;
+use sink::{Counter, Negate, SliceSink};
+#[cfg(cdrle_allocator_api)]
+#[cfg_attr(docsrs, doc(cfg(feature = "allocator_api")))]
+pub use allocator::{compress_in, decompress_in};
+#[cfg(feature = "crc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "crc")))]
+pub use checked::{compress_checked, decompress_checked};
+pub use chunks::{decompress_chunks, DecodeOrSink};
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub use codec::{Cdrle, Decoder, Encoder};
+pub use compile_time::{compress_const, compressed_len};
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub use frame::{decode_frames, encode_frames};
+pub use iter::{compress_iter, decode_tokens, decompress_iter, runs, CompressIter, DecodeTokens, DecompressIter, Run, Runs, Token};
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub use lenprefixed::{compress_lenprefixed, decompress_lenprefixed};
+pub use options::{CdrleOptions, RleConfig};
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub use packed::{compress_packed, decompress_packed};
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub use progress::{compress_with_progress, decompress_with_progress};
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub use seek::{build_index, decompress_at, decompress_prefix, DecodeIndex};
+pub use sink::Sink;
+#[cfg(feature = "parallel")]
+#[cfg_attr(docsrs, doc(cfg(feature = "parallel")))]
+pub use parallel::{compress_parallel, decompress_parallel};
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub use stats::{compress_with_stats, compression_ratio, run_histogram, CompressStats, RunHistogram};
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub use stream::{Compressor, Decompressor};
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub use tagged::{compress_best, compress_tagged, decompress_auto, decompress_best, Format};
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub use typed::{compress_typed, Compressed};
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub use v2::{compress_v2, compress_v2_sparse, decompress_v2, decompress_v2_sparse};
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+pub use io::{compress_file, compress_reader, compress_to_writer, decompress_bufread, decompress_file, decompress_to_writer, CompressWriter, DecompressReader, StreamingDecompressor};
+
 pub const MAX_ZERO_RUN: usize = 128; // 0x00 runs: 1..=128
 pub const MAX_FF_RUN: usize = 32;    // 0xFF runs: 1..=32
 
-/// Canonical decoding errors (exhaustive by construction).
+// Both caps must fit the 7-bit CONTROL length (`len - 1 <= 0x7F`); otherwise
+// `emit_run` would mask off high bits and silently corrupt streams.
+const _: () = assert!(MAX_ZERO_RUN >= 1 && MAX_ZERO_RUN <= 128, "MAX_ZERO_RUN must fit the 7-bit CONTROL length");
+const _: () = assert!(MAX_FF_RUN >= 1 && MAX_FF_RUN <= 128, "MAX_FF_RUN must fit the 7-bit CONTROL length");
+
+/// The CONTROL byte of a run of `len` bytes: bit7 is `is_ff`, the low 7 bits
+/// hold `len - 1`. Inverse of [`decode_control`].
+///
+/// Panics unless `len` is in `1..=128` (the 7-bit field; the decoder further
+/// rejects 0xFF runs longer than `MAX_FF_RUN`).
+pub const fn encode_control(is_ff: bool, len: usize) -> u8 {
+    assert!(len >= 1 && len <= 128, "run length must be in 1..=128");
+    ((len - 1) as u8 & 0x7F) | (is_ff as u8) << 7
+}
+
+/// Splits a CONTROL byte into `(is_ff, len)`, `len` in `1..=128`. Any byte
+/// decodes; whether the length is valid for its run byte is up to the caller.
+pub const fn decode_control(ctrl: u8) -> (bool, usize) {
+    (ctrl & 0x80 != 0, (ctrl & 0x7F) as usize + 1)
+}
+
+/// Appends one decoded run to `out`: `len` copies of 0xFF if `is_ff`, else of
+/// 0x00. The building block of a hand-rolled decoder; pair with
+/// [`decode_control`]. The length check is the one `decompress` applies to
+/// each run token.
+///
+/// Errors:
+/// - InvalidRunLength{len}  (`len` is 0, or above `MAX_FF_RUN`/`MAX_ZERO_RUN`)
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+#[inline]
+pub fn apply_run(out: &mut Vec<u8>, is_ff: bool, len: usize) -> Result<(), Error> {
+    let b = decode::run_byte(&RleConfig::new(), is_ff as usize, len)?;
+    out.push_fill(b, len);
+    Ok(())
+}
+
+/// Worst-case expansion of the format, `WORST_CASE_NUMERATOR / WORST_CASE_DENOMINATOR`
+/// (2/1): a lone 0x00 or 0xFF byte becomes a 2-byte run token, and nothing costs more.
+/// `max_compressed_len(n)` is `n * WORST_CASE_NUMERATOR / WORST_CASE_DENOMINATOR`.
+pub const WORST_CASE_NUMERATOR: usize = 2;
+/// See [`WORST_CASE_NUMERATOR`].
+pub const WORST_CASE_DENOMINATOR: usize = 1;
+
+/// Built-in cap on the decoded size of allocating decoders (1 GiB), so that a
+/// small adversarial stream cannot make `decompress` attempt a huge allocation.
+/// Raise or lower it per call with [`CdrleOptions::max_output`].
+pub const DEFAULT_MAX_OUTPUT: usize = 1 << 30;
+
+/// Canonical decoding errors (exhaustive by construction), plus the
+/// capacity error of the fixed-buffer APIs.
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Error {
     /// The compressed stream ended right after a run marker (0x00),
     /// i.e. there was no CONTROL byte following it.
     RunMarkerWithoutControl,
     /// The CONTROL byte denotes an FF-run length > 32.
-    /// (Decoded as len = (control & 0x7F) + 1; this error can ONLY occur for FF runs.)
+    /// (Decoded as len = (control & 0x7F) + 1; with the default `RleConfig` this
+    /// error can ONLY occur for FF runs, otherwise for any run past its configured max.)
     InvalidRunLength { len: usize },
+    /// A caller-provided output buffer cannot hold the result;
+    /// `needed` is the exact number of bytes required.
+    OutputTooSmall { needed: usize },
+    /// Decoding would produce more than the caller-supplied `limit` bytes
+    /// (`decompress_with_limit` and friends); the built-in cap reports `OutputTooLarge`.
+    OutputLimitExceeded { limit: usize },
+    /// Decoding would produce more than the built-in safety cap of `max` bytes
+    /// (`DEFAULT_MAX_OUTPUT` unless set with `CdrleOptions::max_output`).
+    OutputTooLarge { max: usize },
+    /// A tagged stream is empty or starts with an unknown mode tag.
+    UnknownFormat,
+    /// A framed container's length prefix, frame, or trailer runs past the end of the buffer.
+    TruncatedFrame,
+    /// The CRC32 trailer does not match the decoded data.
+    ChecksumMismatch { expected: u32, found: u32 },
+    /// A v2 run length (LEB128 varint) is cut off by the end of the stream or overflows `usize`.
+    TruncatedVarint,
+    /// The stream is well-formed but decodes to `actual` bytes instead of the `expected` length.
+    LengthMismatch { expected: usize, actual: usize },
+    /// The stream decodes, but is not the encoding `compress` produces (see `decompress_strict`).
+    NonCanonical,
+    /// `InvalidRunLength` located, from `decompress_detailed`: the CONTROL byte
+    /// at compressed offset `offset` reads `control` as stored (before any header
+    /// un-negation) and denotes a run of `len`.
+    InvalidRunLengthAt { len: usize, offset: usize, control: u8 },
 }
 
-/// Compresses `input` and XOR-negates the first 4 bytes of the *compressed* stream.
-pub fn compress(input: &[u8]) -> Vec<u8> {
-    let mut out = Vec::with_capacity(input.len()); // lower bound; worst case ~2×
-    let mut zero = 0usize;
-    let mut ff = 0usize;
-
-    #[inline]
-    fn emit_run(out: &mut Vec<u8>, is_ff: bool, n: usize) {
-        debug_assert!(n >= 1);
-        debug_assert!((!is_ff && n <= MAX_ZERO_RUN) || (is_ff && n <= MAX_FF_RUN));
-        let mut ctrl = ((n as u8) - 1) & 0x7f;
-        if is_ff { ctrl |= 0x80; }
-        out.push(0x00);
-        out.push(ctrl);
-    }
-
-    #[inline]
-    fn flush(out: &mut Vec<u8>, zero: &mut usize, ff: &mut usize) {
-        if *ff != 0 { emit_run(out, true, *ff); *ff = 0; }
-        if *zero != 0 { emit_run(out, false, *zero); *zero = 0; }
-    }
-
-    for &b in input {
-        match b {
-            0x00 => {
-                if ff != 0 { emit_run(&mut out, true, ff); ff = 0; }
-                zero += 1;
-                if zero == MAX_ZERO_RUN { emit_run(&mut out, false, MAX_ZERO_RUN); zero = 0; }
-            }
-            0xFF => {
-                if zero != 0 { emit_run(&mut out, false, zero); zero = 0; }
-                ff += 1;
-                if ff == MAX_FF_RUN { emit_run(&mut out, true, MAX_FF_RUN); ff = 0; }
+impl Error {
+    /// Whether retrying can succeed without the bytes already received being
+    /// different: `true` when the stream was cut short (more input may complete
+    /// it) or a size bound was hit (more room or a higher limit suffices);
+    /// `false` when the bytes themselves are corrupt or of the wrong kind.
+    ///
+    /// The match is exhaustive, so a new variant cannot go unclassified.
+    pub const fn is_recoverable(&self) -> bool {
+        match self {
+            // Truncation: the stream ends mid-token or mid-frame.
+            Error::RunMarkerWithoutControl | Error::TruncatedFrame | Error::TruncatedVarint => true,
+            // Size bounds: the stream is fine, the destination or limit is too small.
+            Error::OutputTooSmall { .. } | Error::OutputLimitExceeded { .. } | Error::OutputTooLarge { .. } => true,
+            // Corruption: no amount of extra input or room makes these bytes decode.
+            Error::InvalidRunLength { .. } | Error::InvalidRunLengthAt { .. } | Error::ChecksumMismatch { .. } => false,
+            // Wrong data: a valid-looking stream that is not what the caller expects.
+            Error::UnknownFormat | Error::LengthMismatch { .. } | Error::NonCanonical => false,
+        }
+    }
+}
+
+/// Formats with `core::fmt` only, so errors can be written to any
+/// `core::fmt::Write` sink in `no_std` builds, with or without `alloc`.
+impl core::fmt::Display for Error {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match *self {
+            Error::RunMarkerWithoutControl => f.write_str("run marker (0x00) without CONTROL byte at end of stream"),
+            Error::InvalidRunLength { len } => write!(f, "run length {} exceeds the maximum for its run byte", len),
+            Error::OutputTooSmall { needed } => write!(f, "output buffer too small ({} bytes needed)", needed),
+            Error::OutputLimitExceeded { limit } => write!(f, "decoded output exceeds limit of {} bytes", limit),
+            Error::OutputTooLarge { max } => write!(f, "decoded output exceeds safety cap of {} bytes", max),
+            Error::UnknownFormat => f.write_str("missing or unknown stream mode tag"),
+            Error::TruncatedFrame => f.write_str("frame runs past the end of the buffer"),
+            Error::ChecksumMismatch { expected, found } => write!(f, "checksum mismatch: expected {:08x}, found {:08x}", expected, found),
+            Error::TruncatedVarint => f.write_str("truncated or overlong varint run length"),
+            Error::LengthMismatch { expected, actual } => write!(f, "decoded length {} does not match expected {}", actual, expected),
+            Error::NonCanonical => f.write_str("stream is not in canonical form"),
+            Error::InvalidRunLengthAt { len, offset, control } => {
+                write!(f, "run length {} exceeds the maximum for its run byte (CONTROL {:#04x} at offset {})", len, control, offset)
             }
-            _ => { flush(&mut out, &mut zero, &mut ff); out.push(b); }
         }
     }
-    flush(&mut out, &mut zero, &mut ff);
+}
+
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+impl std::error::Error for Error {}
+
+/// Compresses `input` and XOR-negates the first 4 bytes of the *compressed* stream
+/// (all of it, if it is shorter than 4 bytes).
+///
+/// Output is byte-for-byte stable: tokens appear in input order, and a run is
+/// emitted as soon as a different byte ends it, so `[0, 0, 0xFF, 0xFF, 1]`
+/// always encodes as the zero run, the 0xFF run, then the literal.
+///
+/// Streams do not concatenate: the second stream's negated header would be read
+/// as plain bytes mid-stream. Keep segments apart (e.g. with `encode_frames`) and
+/// decode them with `decompress_multi`.
+///
+/// The output starts at `input.len()` bytes of capacity and grows if the input
+/// expands (at most 2x), which measured faster than sizing it exactly; use
+/// `compress_exact` when a single allocation matters more than speed.
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub fn compress(input: &[u8]) -> Vec<u8> {
+    if let Some(&b) = input.first() {
+        if (b == 0x00 || b == 0xFF) && scan::run_len(input, b) == input.len() {
+            return encode::encode_single_run(b, input.len());
+        }
+    }
+    compress_g::<MAX_ZERO_RUN, MAX_FF_RUN>(input)
+}
+
+/// Like `compress`, but with the longest 0x00 run `Z` and the longest 0xFF run
+/// `F` fixed at compile time; `compress` is `compress_g::<128, 32>` (less its
+/// single-run fast path). Decode the result with `decompress_g::<Z, F>`.
+///
+/// Each instantiation is its own codec with constant caps, so several can live
+/// in one binary without passing an `RleConfig` around. Caps outside `1..=128`
+/// do not fit the CONTROL byte and are rejected at compile time:
+///
+/// ```compile_fail
+/// cdrle::compress_g::<129, 32>(&[0; 200]);
+/// ```
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub fn compress_g<const Z: usize, const F: usize>(input: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(input.len()); // lower bound; worst case ~2×
+    let mut sink = Negate { inner: &mut out, left: 4 };
+    let mut enc = encode::Encoder::new(options::Caps::<Z, F>::CFG);
+    enc.push(input, &mut sink);
+    enc.flush(&mut sink);
+    out
+}
+
+/// Like `compress`, but sizes the output exactly with a `compressed_len` pre-pass,
+/// so it allocates once however much the input expands. The pre-pass costs
+/// more than the reallocations it saves on run-heavy data: see `compress`.
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub fn compress_exact(input: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(compressed_len(input));
+    compress_to_sink(input, &mut out);
+    out
+}
+
+/// Alias of [`compress`], for code written against the `encode`/`decode`
+/// naming of other codecs.
+///
+/// ```
+/// let comp = cdrle::encode(&[1, 0, 0, 0, 2]);
+/// assert_eq!(comp, cdrle::compress(&[1, 0, 0, 0, 2]));
+/// assert_eq!(cdrle::decode(&comp).unwrap(), [1, 0, 0, 0, 2]);
+/// ```
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+#[inline]
+pub fn encode(input: &[u8]) -> Vec<u8> { compress(input) }
+
+/// Alias of [`decompress`], with the same errors.
+///
+/// ```
+/// assert_eq!(cdrle::decode(&cdrle::encode(b"\xFF\xFF\xFFabc")).unwrap(), b"\xFF\xFF\xFFabc");
+/// assert_eq!(cdrle::decode(&[0xFF]), Err(cdrle::Error::RunMarkerWithoutControl));
+/// ```
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+#[inline]
+pub fn decode(comp: &[u8]) -> Result<Vec<u8>, Error> { decompress(comp) }
+
+/// Compresses `input` only if that makes it smaller: returns `None` when
+/// `compressed_len(input) >= input.len()` (e.g. no 0x00/0xFF runs at all), so
+/// the caller can store the raw bytes and skip decoding later. Unlike
+/// `compress_best`, no tag records the choice; the caller keeps track of it.
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub fn compress_if_beneficial(input: &[u8]) -> Option<Vec<u8>> {
+    let n = compressed_len(input);
+    if n >= input.len() {
+        return None;
+    }
+    let mut out = Vec::with_capacity(n);
+    compress_to_sink(input, &mut out);
+    Some(out)
+}
+
+/// Like `compress`, but writes into `out`, reusing its allocation.
+/// `out` is cleared first; on return it holds exactly the compressed stream
+/// (header negation included).
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub fn compress_into(input: &[u8], out: &mut Vec<u8>) {
+    out.clear();
+    compress_to_sink(input, out);
+}
+
+/// Compresses each of `inputs` into its own blob, `compress(inputs[k])` for
+/// every `k`. One scratch buffer is reused across inputs via `compress_into`,
+/// so each blob costs a single exact-size allocation instead of growing.
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub fn compress_batch(inputs: &[&[u8]]) -> Vec<Vec<u8>> {
+    let mut scratch = Vec::new();
+    inputs.iter().map(|input| {
+        compress_into(input, &mut scratch);
+        scratch.as_slice().to_vec()
+    }).collect()
+}
+
+/// Compresses `input` into any [`Sink`], header negation included. Every
+/// one-shot `compress*` entry point is a wrapper over this.
+pub fn compress_to_sink<S: Sink + ?Sized>(input: &[u8], sink: &mut S) {
+    let mut out = Negate { inner: sink, left: 4 };
+    let mut enc = encode::Encoder::default();
+    enc.push(input, &mut out);
+    enc.flush(&mut out);
+}
+
+/// Like `compress`, but with the stream format chosen by `opts`.
+/// Decode the result with `decompress_with` and the same `opts`.
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub fn compress_with(input: &[u8], opts: &CdrleOptions) -> Vec<u8> {
+    let mut out = Vec::with_capacity(input.len());
+    let mut enc = encode::Encoder::default();
+    enc.push(input, &mut out);
+    enc.flush(&mut out);
+    negate_prefix(&mut out, opts.negate_prefix_len);
+    out
+}
+
+/// Like `compress`, but run-encodes the byte values chosen by `cfg`.
+/// Decode the result with `decompress_cfg` and the same `cfg`.
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub fn compress_cfg(input: &[u8], cfg: &RleConfig) -> Vec<u8> {
+    let mut out = Vec::with_capacity(input.len());
+    let mut enc = encode::Encoder::new(*cfg);
+    enc.push(input, &mut out);
+    enc.flush(&mut out);
+    negate_header(&mut out);
+    out
+}
+
+/// Replaces the contents of `buf` with their compressed form, reusing its
+/// allocation.
+///
+/// Output overwrites input that has already been consumed. Where the output
+/// temporarily outgrows the consumed input (interleaved single 0x00/0xFF bytes
+/// cost 2 bytes each), the excess waits in a queue that is written back into
+/// `buf` as soon as input frees the room. Extra memory is therefore bounded by
+/// the largest such overtake, i.e. by how far the compressed prefix ever runs
+/// ahead of the input read so far: nothing for inputs that never expand, at most
+/// `buf.len()` for the worst case (alternating lone 0x00/0xFF). `buf` may
+/// reallocate if the final output is longer than the input.
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub fn compress_in_place(buf: &mut Vec<u8>) {
+    let (w, spill) = compress_in_place_raw(buf);
+    buf.truncate(w);
+    let (a, b) = spill.as_slices();
+    buf.extend_from_slice(a);
+    buf.extend_from_slice(b);
+    negate_header(buf);
+}
+
+/// Encodes `buf` over itself. Returns the bytes written to `buf` and the
+/// output still queued behind them (before the header is negated).
+#[cfg(feature = "alloc")]
+fn compress_in_place_raw(buf: &mut [u8]) -> (usize, alloc::collections::VecDeque<u8>) {
+    let mut sink = InPlace { buf, w: 0, r: 0, spill: Default::default() };
+    let mut enc = encode::Encoder::default();
+    for r in 0..sink.buf.len() {
+        let b = sink.buf[r];
+        sink.consume(r + 1);
+        enc.push_byte(b, &mut sink);
+    }
+    enc.flush(&mut sink);
+    (sink.w, sink.spill)
+}
+
+/// Like `compress`, but takes the input by value and builds the output in its
+/// allocation, via `compress_in_place`. Peak memory is the input's allocation
+/// plus `compress_in_place`'s overtake queue, which stays empty unless a prefix
+/// of the input expands; for input that compresses, the returned Vec is the
+/// input's allocation and nothing else is allocated. Input that expands overall
+/// (mostly isolated 0x00/0xFF bytes) makes the buffer grow, and possibly move.
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub fn compress_owned(mut input: Vec<u8>) -> Vec<u8> {
+    compress_in_place(&mut input);
+    input
+}
 
-    // Negate first 4 bytes of *compressed* stream.
-    let lim = core::cmp::min(4, out.len());
-    for i in 0..lim { out[i] ^= 0xFF; }
+/// Like `compress`, but pulls input from a byte iterator instead of a slice.
+/// The output is identical to `compress(&iter.into_iter().collect::<Vec<_>>())`.
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub fn compress_from_iter<I: IntoIterator<Item = u8>>(iter: I) -> Vec<u8> {
+    let iter = iter.into_iter();
+    let mut out = Vec::with_capacity(iter.size_hint().0);
+    let mut enc = encode::Encoder::default();
+    for b in iter { enc.push_byte(b, &mut out); }
+    enc.flush(&mut out);
+    negate_header(&mut out);
     out
 }
 
+/// Compresses `input` into the fixed buffer `out` without allocating and
+/// returns the number of bytes written. The output is identical to `compress`.
+///
+/// Errors:
+/// - OutputTooSmall{needed}   (`out.len() < needed`; `out` contents are then unspecified)
+pub fn compress_slice(input: &[u8], out: &mut [u8]) -> Result<usize, Error> {
+    let mut sink = SliceSink { buf: out, len: 0 };
+    compress_to_sink(input, &mut sink);
+    if sink.len > sink.buf.len() {
+        return Err(Error::OutputTooSmall { needed: sink.len });
+    }
+    Ok(sink.len)
+}
+
+/// Upper bound on the compressed length of any `input_len`-byte input, usable for
+/// `[u8; N]` sizing.
+///
+/// Derivation: a literal costs 1 byte per input byte; a run costs 2 bytes
+/// (marker + CONTROL) and covers at least 1 input byte; so no input byte costs
+/// more than 2 output bytes. The bound is tight: alternating single 0x00 and 0xFF
+/// bytes (`00 FF 00 FF ...`) compress to exactly `2 * input_len`. The header
+/// negation does not change the length.
+pub const fn max_compressed_len(input_len: usize) -> usize {
+    input_len.saturating_mul(WORST_CASE_NUMERATOR) / WORST_CASE_DENOMINATOR
+}
+
 /// Decompresses `comp` produced by `compress`.
 /// Errors:
 /// - RunMarkerWithoutControl  (0x00 as final byte)
 /// - InvalidRunLength{len}    (FF-run with len > 32)
+/// - OutputTooLarge{max}      (decoded size would pass `DEFAULT_MAX_OUTPUT`)
+///
+/// A `decompressed_len` pre-pass sizes the output exactly, so it is allocated
+/// once, and only after the whole stream has been validated. The cap is checked
+/// against that total up front: a stream decoding past `DEFAULT_MAX_OUTPUT`
+/// fails with `OutputTooLarge { max: DEFAULT_MAX_OUTPUT }` before anything is
+/// allocated. (`OutputLimitExceeded` is only for limits the caller passes, as
+/// to `decompress_with_limit`.)
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
 pub fn decompress(comp: &[u8]) -> Result<Vec<u8>, Error> {
-    let mut out = Vec::with_capacity(comp.len()); // conservative lower bound
-    let mut i = 0usize;
+    // Validating pre-pass: errors surface before any allocation, and the
+    // output is reserved exactly once.
+    let n = decompressed_len(comp)?;
+    if n > DEFAULT_MAX_OUTPUT {
+        return Err(Error::OutputTooLarge { max: DEFAULT_MAX_OUTPUT });
+    }
+    let mut out = Vec::with_capacity(n);
+    decode::Decoder::default().push(comp, &mut out)?;
+    Ok(out)
+}
 
-    #[inline]
-    fn read_unneg(comp: &[u8], i: &mut usize) -> u8 {
-        let mut b = comp[*i];
-        if *i < 4 { b ^= 0xFF; }
-        *i += 1;
-        b
+/// Like `decompress`, for a stream produced by `compress_g::<Z, F>`. Runs longer
+/// than the caps fail with `InvalidRunLength`, as does every other error of
+/// `decompress`; `Z`/`F` are checked at compile time just as in `compress_g`.
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub fn decompress_g<const Z: usize, const F: usize>(comp: &[u8]) -> Result<Vec<u8>, Error> {
+    let mut out = Vec::with_capacity(comp.len());
+    let mut dec = decode::Decoder::default().config(options::Caps::<Z, F>::CFG);
+    dec.push(comp, &mut out)?;
+    dec.finish()?;
+    Ok(out)
+}
+
+/// Decompresses `comp` into the fixed buffer `out` without allocating and
+/// returns the decoded length. Because the caller bounds the output, this also
+/// guards against decompression bombs.
+///
+/// Errors:
+/// - RunMarkerWithoutControl  (0x00 as final byte)
+/// - InvalidRunLength{len}    (FF-run with len > 32)
+/// - OutputTooSmall{needed}   (`out.len() < needed`; `out` contents are then unspecified)
+pub fn decompress_slice(comp: &[u8], out: &mut [u8]) -> Result<usize, Error> {
+    let mut sink = SliceSink { buf: out, len: 0 };
+    let mut dec = decode::Decoder::with_limit(usize::MAX);
+    dec.push(comp, &mut sink)?;
+    dec.finish()?;
+    if sink.len > sink.buf.len() {
+        return Err(Error::OutputTooSmall { needed: sink.len });
     }
+    Ok(sink.len)
+}
 
-    while i < comp.len() {
-        let b = read_unneg(comp, &mut i);
-        if b != 0x00 {
-            out.push(b);
-            continue;
+/// Returns the length `decompress` would produce for `comp`, without allocating.
+/// Validates the stream exactly like `decompress`.
+///
+/// Errors:
+/// - RunMarkerWithoutControl  (0x00 as final byte)
+/// - InvalidRunLength{len}    (FF-run with len > 32)
+pub fn decompressed_len(comp: &[u8]) -> Result<usize, Error> {
+    let mut n = Counter::default();
+    let mut dec = decode::Decoder::with_limit(usize::MAX);
+    dec.push(comp, &mut n)?;
+    dec.finish()?;
+    Ok(n.0)
+}
+
+/// Checks that `comp` would decode: every run marker has a CONTROL byte and
+/// every FF-run length is at most `MAX_FF_RUN`. Allocates nothing and expands no
+/// runs, so it is cheap to call on untrusted input before `decompress`.
+///
+/// Errors: the error `decompress` would return (apart from its output cap).
+pub fn validate(comp: &[u8]) -> Result<(), Error> {
+    decode_tokens(comp).try_for_each(|t| t.map(drop))
+}
+
+/// Returns whether `validate(comp)` succeeds.
+pub fn is_valid(comp: &[u8]) -> bool { validate(comp).is_ok() }
+
+/// Like `decompress`, for a stream produced by `compress_with` with the same `opts`.
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub fn decompress_with(comp: &[u8], opts: &CdrleOptions) -> Result<Vec<u8>, Error> {
+    let mut out = Vec::with_capacity(comp.len());
+    let mut dec = decode::Decoder::default().negating(opts.negate_prefix_len).capped(opts.max_output);
+    dec.push(comp, &mut out)?;
+    dec.finish()?;
+    Ok(out)
+}
+
+/// Compresses `input` as plain 0x00/0xFF RLE, without the header negation:
+/// the `negate_header(false)` format, for decoders that do not expect it.
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub fn compress_plain(input: &[u8]) -> Vec<u8> {
+    compress_with(input, &CdrleOptions::new().negate_header(false))
+}
+
+/// Decompresses plain RLE produced by `compress_plain`.
+///
+/// Errors: as `decompress`.
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub fn decompress_plain(comp: &[u8]) -> Result<Vec<u8>, Error> {
+    decompress_with(comp, &CdrleOptions::new().negate_header(false))
+}
+
+/// Like `decompress`, for a stream produced by `compress_cfg` with the same `cfg`.
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub fn decompress_cfg(comp: &[u8], cfg: &RleConfig) -> Result<Vec<u8>, Error> {
+    let mut out = Vec::with_capacity(comp.len());
+    let mut dec = decode::Decoder::default().config(*cfg);
+    dec.push(comp, &mut out)?;
+    dec.finish()?;
+    Ok(out)
+}
+
+/// Like `decompress`, but fails as soon as the output would exceed `max_out` bytes,
+/// before allocating for the offending token. Use this for untrusted input: a
+/// single 2-byte run expands to up to 128 bytes.
+///
+/// `max_out` replaces the built-in `DEFAULT_MAX_OUTPUT` cap.
+///
+/// Errors: as `decompress`, plus
+/// - OutputLimitExceeded{limit}  (decoded size would pass `max_out`)
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub fn decompress_with_limit(comp: &[u8], max_out: usize) -> Result<Vec<u8>, Error> {
+    let mut out = Vec::with_capacity(comp.len().min(max_out));
+    let mut dec = decode::Decoder::with_limit(max_out);
+    dec.push(comp, &mut out)?;
+    dec.finish()?;
+    Ok(out)
+}
+
+/// Returns whether `input` survives `compress` then `decompress` unchanged.
+/// Never panics; a decode error counts as a mismatch.
+///
+/// The decode is bounded by `input.len()` rather than `DEFAULT_MAX_OUTPUT`, so
+/// valid inputs of any size verify; a stream decoding to more is a mismatch anyway.
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub fn verify_roundtrip(input: &[u8]) -> bool {
+    matches!(decompress_with_limit(&compress(input), input.len()), Ok(d) if d == input)
+}
+
+/// Like `decompress`, but writes into `out`, reusing its allocation.
+/// `out` is cleared first. On error, `out` is left holding the bytes decoded
+/// before the failing token.
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub fn decompress_into(comp: &[u8], out: &mut Vec<u8>) -> Result<(), Error> {
+    let mut d = Decompressor::with_buffer(core::mem::take(out));
+    let r = d.push(comp);
+    let f = d.finish_into(out);
+    r.and(f)
+}
+
+/// Like `decompress_into`, also returning the decoded length (`out.len()` on
+/// success), for accounting against `out.capacity()` when `out` is reused.
+///
+/// On error, as with `decompress_into`, `out` holds the bytes decoded before the
+/// failing token; its length is then the count of valid bytes, and nothing is
+/// returned.
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub fn decompress_into_exact(comp: &[u8], out: &mut Vec<u8>) -> Result<usize, Error> {
+    decompress_into(comp, out).map(|()| out.len())
+}
+
+/// Like `decompress`, but appends to `out`, keeping its existing contents.
+/// On error, `out` is left holding its old contents plus the bytes decoded
+/// before the failing token.
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub fn decompress_append(comp: &[u8], out: &mut Vec<u8>) -> Result<(), Error> {
+    let mut dec = decode::Decoder::default();
+    dec.push(comp, out)?;
+    dec.finish()
+}
+
+/// Like `decompress`, but borrows `comp` when the decoded bytes are `comp` itself.
+///
+/// That needs a stream with no run markers whose first 4 bytes decode to
+/// themselves. The header negation stores every one of those bytes as `b ^ 0xFF`,
+/// which never equals `b`, so the only stream that is ever borrowed is the empty
+/// one: even a literal-only stream such as `compress(b"abcdef")` comes back
+/// `Cow::Owned`. Errors are those of `decompress`.
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub fn decompress_cow(comp: &[u8]) -> Result<Cow<'_, [u8]>, Error> {
+    if comp.is_empty() {
+        return Ok(Cow::Borrowed(comp));
+    }
+    decompress(comp).map(Cow::Owned)
+}
+
+/// Re-encodes a valid stream in the form `compress` produces, e.g. merging
+/// adjacent runs another encoder split early: the result equals
+/// `compress(&decompress(comp)?)`. Runs are carried over without being expanded,
+/// so the decoded data is never materialized (nor capped).
+///
+/// Errors: as `decompress` (the output cap aside).
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub fn recompress(comp: &[u8]) -> Result<Vec<u8>, Error> {
+    const RUNS: [[u8; MAX_ZERO_RUN]; 2] = [[0x00; MAX_ZERO_RUN], [0xFF; MAX_ZERO_RUN]];
+    let mut out = Vec::with_capacity(comp.len());
+    let mut sink = Negate { inner: &mut out, left: 4 };
+    let mut enc = encode::Encoder::default();
+    for t in decode_tokens(comp) {
+        match t? {
+            Token::Literal(b) => enc.push(&[b], &mut sink),
+            Token::ZeroRun(n) => enc.push(&RUNS[0][..n], &mut sink),
+            Token::FfRun(n) => enc.push(&RUNS[1][..n], &mut sink),
         }
-        if i >= comp.len() {
-            return Err(Error::RunMarkerWithoutControl);
+    }
+    enc.flush(&mut sink);
+    Ok(out)
+}
+
+/// Like `decompress`, but accepts only the one encoding `compress` gives each
+/// input, so equal data always has equal compressed bytes. Rejected with
+/// `NonCanonical`, although `decompress` accepts them:
+/// - a run followed by another run of the same byte when the first is shorter
+///   than the maximum (128 zeros, 32 0xFF bytes), as the two should be merged;
+/// - a literal 0xFF (un-negated), which should be an 0xFF run of 1.
+///
+/// Errors: as `decompress`, plus
+/// - NonCanonical  (one of the patterns above)
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub fn decompress_strict(comp: &[u8]) -> Result<Vec<u8>, Error> {
+    let mut out = Vec::with_capacity(comp.len());
+    let mut open = None; // run byte of a preceding short run that must not continue
+    for t in decode_tokens(comp) {
+        let (b, n, max) = match t? {
+            Token::Literal(0xFF) => return Err(Error::NonCanonical),
+            Token::Literal(b) => (b, 1, 0),
+            Token::ZeroRun(n) => (0x00, n, MAX_ZERO_RUN),
+            Token::FfRun(n) => (0xFF, n, MAX_FF_RUN),
+        };
+        if max != 0 && open == Some(b) {
+            return Err(Error::NonCanonical);
         }
-        let c = read_unneg(comp, &mut i);
-        let is_ff = (c & 0x80) != 0;
-        let len = (c & 0x7F) as usize + 1;
-        if is_ff && len > MAX_FF_RUN {
-            return Err(Error::InvalidRunLength { len });
+        open = if max != 0 && n < max { Some(b) } else { None };
+        if n > DEFAULT_MAX_OUTPUT - out.len() {
+            return Err(Error::OutputTooLarge { max: DEFAULT_MAX_OUTPUT });
         }
-        let fill = if is_ff { 0xFF } else { 0x00 };
-        let base = out.len();
-        out.resize(base + len, fill);
+        out.resize(out.len() + n, b);
+    }
+    Ok(out)
+}
+
+/// Like `decompress`, for data whose decoded length is known to be `expected_len`.
+/// The length is checked by a pre-pass before anything is allocated, and the
+/// output is then reserved exactly. `expected_len` replaces the built-in
+/// `DEFAULT_MAX_OUTPUT` cap.
+///
+/// Errors: as `decompress`, plus
+/// - LengthMismatch{expected, actual}  (the stream decodes to another length)
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub fn decompress_exact(comp: &[u8], expected_len: usize) -> Result<Vec<u8>, Error> {
+    let actual = decompressed_len(comp)?;
+    if actual != expected_len {
+        return Err(Error::LengthMismatch { expected: expected_len, actual });
+    }
+    let mut out = Vec::with_capacity(expected_len);
+    decode::Decoder::with_limit(expected_len).push(comp, &mut out)?;
+    Ok(out)
+}
+
+/// Decodes as much of `comp` as is well-formed, for recovering damaged data.
+/// Returns the decoded bytes of every token before the first bad one, which is
+/// the output `decompress` would give for that prefix of the stream, and the
+/// error that stopped decoding (`None` if the whole stream decoded).
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub fn decompress_lossy(comp: &[u8]) -> (Vec<u8>, Option<Error>) {
+    let mut out = Vec::with_capacity(comp.len());
+    let mut dec = decode::Decoder::default();
+    let r = dec.push(comp, &mut out).and_then(|()| dec.finish());
+    (out, r.err())
+}
+
+/// Decodes each of `streams` independently and concatenates the results, so
+/// `decompress_multi(&[&compress(a), &compress(b)])` is `[a, b].concat()`.
+///
+/// Errors: the first error of `decompress` on any stream (each is capped at
+/// `DEFAULT_MAX_OUTPUT` on its own).
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub fn decompress_multi(streams: &[&[u8]]) -> Result<Vec<u8>, Error> {
+    let mut out = Vec::with_capacity(streams.iter().map(|s| s.len()).sum());
+    for s in streams {
+        decompress_append(s, &mut out)?;
     }
+    Ok(out)
+}
+
+/// Like `decompress`, but reports a bad run as `InvalidRunLengthAt`, with the
+/// offset and value of its CONTROL byte, for diagnosing corrupt streams. Valid
+/// streams decode exactly as fast as with `decompress`: the offset is only
+/// searched for once decoding has failed.
+///
+/// Errors: as `decompress`, with `InvalidRunLengthAt` in place of `InvalidRunLength`.
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub fn decompress_detailed(comp: &[u8]) -> Result<Vec<u8>, Error> {
+    decompress(comp).map_err(|e| match e {
+        Error::InvalidRunLength { len } => {
+            let mut toks = decode_tokens(comp);
+            while let Some(Ok(_)) = toks.next() {}
+            let offset = toks.offset() - 1; // just past the CONTROL byte
+            Error::InvalidRunLengthAt { len, offset, control: comp[offset] }
+        }
+        e => e,
+    })
+}
 
+/// Like `decompress_multi`, for `compress` outputs laid end to end in one buffer:
+/// stream `k` is the next `stream_lengths[k]` bytes of `comp`, and its header
+/// negation is applied relative to its own start.
+///
+/// Errors: as `decompress_multi`, plus
+/// - TruncatedFrame  (`stream_lengths` does not sum to exactly `comp.len()`)
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub fn decompress_all(comp: &[u8], stream_lengths: &[usize]) -> Result<Vec<u8>, Error> {
+    let mut out = Vec::with_capacity(comp.len());
+    let mut rest = comp;
+    for &n in stream_lengths {
+        if n > rest.len() {
+            return Err(Error::TruncatedFrame);
+        }
+        let (s, tail) = rest.split_at(n);
+        decompress_append(s, &mut out)?;
+        rest = tail;
+    }
+    if !rest.is_empty() {
+        return Err(Error::TruncatedFrame);
+    }
     Ok(out)
 }
 
-#[cfg(test)]
+/// Decodes the first block of `comp` (see `Compressor::flush_block`) and returns
+/// it with the number of bytes consumed, terminator included. A block runs to its
+/// terminator or, for the final unterminated block, to the end of `comp`, so
+/// repeated calls on the remaining bytes walk a sequence of blocks.
+///
+/// Errors: as `decompress`, for the bytes of the block.
+#[cfg(feature = "alloc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc")))]
+pub fn decompress_block(comp: &[u8]) -> Result<(Vec<u8>, usize), Error> {
+    let unneg = |i: usize| if i < 4 { comp[i] ^ 0xFF } else { comp[i] };
+    let mut out = Vec::with_capacity(comp.len());
+    let mut dec = decode::Decoder::default();
+    let mut i = 0;
+    while i < comp.len() {
+        if unneg(i) == encode::BLOCK_END[0] && i + 1 < comp.len() && unneg(i + 1) == encode::BLOCK_END[1] {
+            return Ok((out, i + 2));
+        }
+        // One token: a literal, or a run marker and its CONTROL byte.
+        let n = if unneg(i) == 0x00 { 2 } else { 1 }.min(comp.len() - i);
+        dec.push(&comp[i..i + n], &mut out)?;
+        i += n;
+    }
+    dec.finish()?;
+    Ok((out, i))
+}
+
+#[cfg(all(test, feature = "alloc"))]
+#[allow(clippy::useless_vec)] // the original `zeros`/`ffs` tests build their inputs with `vec!`
 mod tests {
     use super::*;
     use alloc::vec;
 
     fn rt(v: &[u8]) {
         let c = compress(v);
+        assert_eq!(compressed_len(v), c.len(), "in:{:x?}", v);
         let d = decompress(&c).unwrap();
         assert_eq!(v, d.as_slice(), "in:{:x?} cmp:{:x?} dec:{:x?}", v, c, d);
     }
@@ -130,6 +896,668 @@ mod tests {
     #[test] fn mixed() {
         rt(&[0,0,0,0, 42, 0xFF,0xFF,0xFF, 1,2,3, 0, 0xFF, 0, 0xAA,0xBB, 0, 0xFF]);
     }
+    #[test] fn compress_into_reuses_buffer() {
+        let v = [0,0,0,0, 42, 0xFF,0xFF,0xFF, 1,2,3, 0, 0xFF, 0, 0xAA,0xBB, 0, 0xFF];
+        let mut out = vec![0xEE; 64];
+        let ptr = out.as_ptr();
+        compress_into(&v, &mut out);
+        assert_eq!(out, compress(&v));
+        assert_eq!(out.as_ptr(), ptr);
+        compress_into(&[1], &mut out);
+        assert_eq!(out, compress(&[1]));
+    }
+    #[test] fn decompress_into_reuses_buffer() {
+        let v = [0,0,0,0, 42, 0xFF,0xFF,0xFF, 1,2,3, 0, 0xFF, 0, 0xAA,0xBB, 0, 0xFF];
+        let mut out = vec![0xEE; 64];
+        let ptr = out.as_ptr();
+        decompress_into(&compress(&v), &mut out).unwrap();
+        assert_eq!(out, v);
+        assert_eq!(out.as_ptr(), ptr);
+    }
+    #[test] fn decompress_into_keeps_partial_output() {
+        let mut c = compress(&[7, 8, 9, 10, 11]);
+        c.push(0x00); // dangling marker
+        let mut out = vec![0xEE; 3];
+        assert_eq!(decompress_into(&c, &mut out), Err(Error::RunMarkerWithoutControl));
+        assert_eq!(out, [7, 8, 9, 10, 11]);
+    }
+    #[test] fn decompress_into_exact_reports_length() {
+        let v = noise(8, 1000);
+        let mut out = Vec::with_capacity(4096);
+        out.extend_from_slice(&[0xEE; 10]);
+        assert_eq!(decompress_into_exact(&compress(&v), &mut out), Ok(1000));
+        assert_eq!(out, v);
+        assert_eq!(out.capacity(), 4096);
+        let mut c = compress(&[7, 8, 9, 10, 11]);
+        c.push(0x00); // dangling marker
+        assert_eq!(decompress_into_exact(&c, &mut out), Err(Error::RunMarkerWithoutControl));
+        assert_eq!(out, [7, 8, 9, 10, 11]);
+        assert_eq!(decompress_into_exact(&[], &mut out), Ok(0));
+    }
+    #[test] fn decompress_append_concatenates() {
+        let segs: [&[u8]; 3] = [&[0, 0, 0, 1], &[], &[0xFF, 0xFF, 2, 0]];
+        let mut out = vec![9];
+        for s in segs { decompress_append(&compress(s), &mut out).unwrap(); }
+        assert_eq!(out, [9, 0, 0, 0, 1, 0xFF, 0xFF, 2, 0]);
+
+        let mut c = compress(&[7, 8, 9, 10]);
+        c.push(0x00); // dangling marker
+        assert_eq!(decompress_append(&c, &mut out), Err(Error::RunMarkerWithoutControl));
+        assert_eq!(out, [9, 0, 0, 0, 1, 0xFF, 0xFF, 2, 0, 7, 8, 9, 10]);
+    }
+    #[test] fn decompress_cow_borrows_only_identity() {
+        assert!(matches!(decompress_cow(&[]), Ok(Cow::Borrowed(b)) if b.is_empty()));
+        for v in [&b"a"[..], b"abcdef", &[0, 0, 1, 0xFF]] {
+            let c = compress(v);
+            assert!(matches!(decompress_cow(&c), Ok(Cow::Owned(ref d)) if d == v), "{:x?}", v);
+        }
+        assert_eq!(decompress_cow(&[1, 0xFF]), Err(Error::RunMarkerWithoutControl));
+    }
+    #[test] fn validate_agrees_with_decompress() {
+        for seed in 0..32 {
+            let c = compress(&noise(seed, 500));
+            assert_eq!(validate(&c), Ok(()));
+            assert!(is_valid(&c));
+        }
+        for a in 0..=255u8 {
+            for b in 0..=255u8 {
+                for c in [&[a][..], &[a, b], &[0xFF, 0xFF, a, b ^ 0xFF, 0x00, b]] {
+                    assert_eq!(validate(c), decompress(c).map(drop), "{:x?}", c);
+                }
+            }
+        }
+        assert!(!is_valid(&[1 ^ 0xFF, 0xFF]));
+    }
+    #[test] fn single_run_fast_path_matches_general() {
+        for b in [0x00, 0xFF] {
+            for n in [1, 2, 3, 31, 32, 33, 64, 127, 128, 129, 256, 1000, 4096 + 5] {
+                let v = vec![b; n];
+                let mut slow = Vec::new();
+                compress_to_sink(&v, &mut slow);
+                assert_eq!(compress(&v), slow, "byte {:x} n {}", b, n);
+                rt(&v);
+            }
+        }
+    }
+    #[test] fn run_order_is_pinned() {
+        // (0x00, 0x01) zero run of 2, (0x00, 0x81) 0xFF run of 2, literal 1; first 4 bytes negated.
+        assert_eq!(compress(&[0, 0, 0xFF, 0xFF, 1]), [0xFF, 0xFE, 0xFF, 0x7E, 0x01]);
+        assert_eq!(compress(&[0xFF, 0xFF, 0, 0, 1]), [0xFF, 0x7E, 0xFF, 0xFE, 0x01]);
+        assert_eq!(compress(&[1, 0, 0xFF, 0, 0, 0, 0, 0]), [0xFE, 0xFF, 0xFF, 0xFF, 0x80, 0x00, 0x04]);
+    }
+    #[test] fn no_control_byte_decodes_to_empty_run() {
+        for c in 0..=255u8 {
+            let comp = [1 ^ 0xFF, 0xFF, c ^ 0xFF];
+            let len = (c & 0x7F) as usize + 1;
+            match decompress(&comp) {
+                Ok(v) => assert_eq!(v.len(), 1 + len, "control {:#04x}", c),
+                Err(e) => assert_eq!((e, c & 0x80 != 0), (Error::InvalidRunLength { len }, true), "control {:#04x}", c),
+            }
+        }
+    }
+    #[test] fn streams_shorter_than_header() {
+        let cases: [(&[u8], &[u8]); 6] = [
+            (&[], &[]),
+            (&[7], &[7 ^ 0xFF]),
+            (&[0], &[0xFF, 0xFF]),
+            (&[0xFF], &[0xFF, 0x7F]),
+            (&[7, 8], &[7 ^ 0xFF, 8 ^ 0xFF]),
+            (&[7, 0], &[7 ^ 0xFF, 0xFF, 0xFF]),
+        ];
+        for (v, c) in cases {
+            assert_eq!(compress(v), c);
+            assert_eq!(decompress(c).unwrap(), v);
+            assert_eq!(decompress_iter(c).collect::<Result<Vec<_>, _>>().unwrap(), v);
+            let mut d = stream::Decompressor::new();
+            for b in c { d.push(&[*b]).unwrap(); }
+            assert_eq!(d.finish().unwrap(), v);
+            let mut buf = [0u8; 3];
+            assert_eq!(compress_slice(v, &mut buf), Ok(c.len()));
+            assert_eq!(&buf[..c.len()], c);
+            assert_eq!(decompress_slice(c, &mut buf), Ok(v.len()));
+            assert_eq!(&buf[..v.len()], v);
+        }
+    }
+    #[test] fn concatenation_needs_decompress_multi() {
+        let (a, b) = (noise(1, 300), [5, 6, 7, 8, 0, 0]);
+        let (ca, cb) = (compress(&a), compress(&b));
+        assert_ne!(decompress(&[&ca[..], &cb[..]].concat()).ok(), Some([&a[..], &b[..]].concat()));
+        assert_eq!(decompress_multi(&[&ca, &cb, &compress(&[])]).unwrap(), [&a[..], &b[..]].concat());
+        assert_eq!(decompress_multi(&[]).unwrap(), []);
+        assert_eq!(decompress_multi(&[&ca, &[1, 0xFF]]), Err(Error::RunMarkerWithoutControl));
+    }
+    #[test] fn compress_owned_reuses_allocation() {
+        let v = noise(6, 5000);
+        let ptr = v.as_ptr();
+        let expected = compress(&v);
+        assert!(expected.len() <= v.len());
+        let c = compress_owned(v);
+        assert_eq!(c, expected);
+        assert_eq!(c.as_ptr(), ptr);
+        let alt: Vec<u8> = (0..100).map(|i| if i % 2 == 0 { 0x00 } else { 0xFF }).collect();
+        assert_eq!(compress_owned(alt.clone()), compress(&alt)); // grows to 2x
+        assert_eq!(compress_owned(Vec::new()), compress(&[]));
+    }
+    #[test] fn compress_batch_matches_compress() {
+        let vs = [noise(4, 200), vec![], vec![0; 300], noise(5, 17)];
+        let inputs: Vec<&[u8]> = vs.iter().map(|v| &v[..]).collect();
+        let out = compress_batch(&inputs);
+        assert_eq!(out.len(), vs.len());
+        for (c, v) in out.iter().zip(&vs) {
+            assert_eq!(c, &compress(v));
+            assert_eq!(c.capacity(), c.len());
+        }
+        assert!(compress_batch(&[]).is_empty());
+    }
+    #[test] fn compress_if_beneficial_skips_incompressible() {
+        assert_eq!(compress_if_beneficial(b"plain text, no nulls"), None);
+        assert_eq!(compress_if_beneficial(&[]), None);
+        assert_eq!(compress_if_beneficial(&[1, 0, 2]), None); // 0 -> 2 bytes
+        assert_eq!(compress_if_beneficial(&[1, 0, 0, 2]), None); // break-even
+        let v = [1, 0, 0, 0, 2];
+        assert_eq!(compress_if_beneficial(&v), Some(compress(&v)));
+    }
+    #[test] fn decompress_detailed_locates_bad_control() {
+        let v = noise(3, 500);
+        let c = compress(&v);
+        assert_eq!(decompress_detailed(&c).unwrap(), v);
+        // Inside the negated header: the stored byte is the negated CONTROL.
+        assert_eq!(decompress_detailed(&[1 ^ 0xFF, 0xFF, 0xA0 ^ 0xFF]), Err(Error::InvalidRunLengthAt { len: 33, offset: 2, control: 0x5F }));
+        let mut bad = c.clone();
+        bad.extend_from_slice(&[7, 0x00, 0xC0]);
+        assert_eq!(decompress_detailed(&bad), Err(Error::InvalidRunLengthAt { len: 65, offset: c.len() + 2, control: 0xC0 }));
+        assert_eq!(decompress(&bad), Err(Error::InvalidRunLength { len: 65 }));
+        assert_eq!(decompress_detailed(&[0xFF]), Err(Error::RunMarkerWithoutControl));
+    }
+    #[test] fn decompress_all_splits_by_length() {
+        let (a, b, c) = (noise(2, 300), [5, 6, 7, 8, 0, 0], [0xFFu8; 3]);
+        let parts = [compress(&a), compress(&b), compress(&[]), compress(&c)];
+        let lens: Vec<usize> = parts.iter().map(|p| p.len()).collect();
+        let packed = parts.concat();
+        assert_eq!(decompress_all(&packed, &lens).unwrap(), [&a[..], &b[..], &c[..]].concat());
+        for (p, v) in parts.iter().zip([&a[..], &b, &[], &c]) {
+            assert_eq!(decompress(p).unwrap(), v);
+        }
+        assert_eq!(decompress_all(&[], &[]).unwrap(), []);
+        assert_eq!(decompress_all(&packed, &lens[..3]), Err(Error::TruncatedFrame));
+        assert_eq!(decompress_all(&packed, &[packed.len() + 1]), Err(Error::TruncatedFrame));
+        // Wrong boundaries misplace the negation: the result is an error or garbage.
+        assert_ne!(decompress_all(&packed, &[lens[0] + 1, packed.len() - lens[0] - 1]).ok(), Some([&a[..], &b[..], &c[..]].concat()));
+    }
+    #[test] fn blocks_decode_independently() {
+        let recs: [&[u8]; 5] = [&[0,0,0,0, 42, 0xFF,0xFF,0xFF, 1,2,3, 0, 0xFF, 0], &[], &[0], &[0xFF; 40], &[9, 9]];
+        let mut c = Compressor::new();
+        for r in &recs[..4] {
+            c.push(r);
+            c.flush_block();
+        }
+        c.push(recs[4]); // final, unterminated block
+        let log = c.finish();
+        let mut rest = &log[..];
+        for r in recs {
+            let (d, used) = decompress_block(rest).unwrap();
+            assert_eq!(d, r);
+            rest = &rest[used..];
+        }
+        assert!(rest.is_empty());
+        assert_eq!(decompress_block(&[]).unwrap(), (vec![], 0));
+        // A record split mid-run still ends its block; the next starts fresh.
+        let mut c = Compressor::new();
+        c.push(&[0, 0]);
+        c.flush_block();
+        c.push(&[0, 5]);
+        let log = c.finish();
+        assert_eq!(decompress_block(&log).unwrap(), (vec![0, 0], 4));
+        assert_eq!(decompress_block(&log[4..]).unwrap(), (vec![0, 5], 3));
+        assert_eq!(decompress_block(&[1 ^ 0xFF, 0xFF]), Err(Error::RunMarkerWithoutControl));
+    }
+    #[test] fn compress_exact_allocates_exactly() {
+        for seed in 0..32 {
+            let x = arb(seed);
+            let c = compress_exact(&x);
+            assert_eq!(c, compress(&x));
+            assert_eq!(c.capacity(), c.len());
+        }
+    }
+    #[test] fn decompress_allocates_exactly() {
+        for seed in 0..32 {
+            let x = arb(seed);
+            let d = decompress(&compress(&x)).unwrap();
+            assert_eq!(d, x);
+            assert_eq!(d.capacity(), x.len());
+        }
+    }
+    #[test] fn decompress_exact_checks_length() {
+        use alloc::string::ToString;
+        let x = noise(3, 700);
+        let c = compress(&x);
+        let d = decompress_exact(&c, x.len()).unwrap();
+        assert_eq!((d.len(), d.capacity()), (x.len(), x.len()));
+        assert_eq!(d, x);
+        assert_eq!(decompress_exact(&c, 699), Err(Error::LengthMismatch { expected: 699, actual: 700 }));
+        assert_eq!(decompress_exact(&c, 701), Err(Error::LengthMismatch { expected: 701, actual: 700 }));
+        assert_eq!(decompress_exact(&[1, 0xFF], 1), Err(Error::RunMarkerWithoutControl));
+        assert_eq!(Error::LengthMismatch { expected: 2, actual: 3 }.to_string(), "decoded length 3 does not match expected 2");
+    }
+    #[test] fn decompress_lossy_recovers_prefix() {
+        let x = noise(7, 2000);
+        let c = compress(&x);
+        assert_eq!(decompress_lossy(&c), (x.clone(), None));
+        for cut in 0..c.len() {
+            let (d, e) = decompress_lossy(&c[..cut]);
+            match e {
+                None => assert_eq!(decompress(&c[..cut]).unwrap(), d),
+                Some(e) => {
+                    assert_eq!(e, Error::RunMarkerWithoutControl);
+                    assert_eq!(decompress(&c[..cut - 1]).unwrap(), d); // drop the dangling marker
+                }
+            }
+            assert!(x.starts_with(&d));
+        }
+        let mut bad = compress(&[1, 2, 3, 4, 5]);
+        bad.extend_from_slice(&[0x00, 0xA0, 6]);
+        assert_eq!(decompress_lossy(&bad), (vec![1, 2, 3, 4, 5], Some(Error::InvalidRunLength { len: 33 })));
+    }
+    #[test] fn recompress_canonicalizes() {
+        // Two 64-zero runs, FF runs of 3 + 2, and a run of 1 zero split in front of a literal.
+        let mut c = vec![0x00, 63, 0x00, 63, 0x00, 0x82, 0x00, 0x81, 0x00, 0x00, 9];
+        negate_prefix(&mut c, 4);
+        let d = decompress(&c).unwrap();
+        let r = recompress(&c).unwrap();
+        assert_eq!(r, compress(&d));
+        assert_eq!(r.len(), 7);
+        for seed in 0..16 {
+            let c = compress(&arb(seed));
+            assert_eq!(recompress(&c).unwrap(), c);
+        }
+        assert_eq!(recompress(&[1, 0xFF]), Err(Error::RunMarkerWithoutControl));
+    }
+    #[test] fn decompress_strict_rejects_non_canonical() {
+        for seed in 0..64 {
+            let x = arb(seed);
+            assert_eq!(decompress_strict(&compress(&x)).unwrap(), x, "seed {}", seed);
+        }
+        let strict = |mut c: Vec<u8>| { negate_prefix(&mut c, 4); decompress_strict(&c) };
+        assert_eq!(strict(vec![0x00, 63, 0x00, 63]), Err(Error::NonCanonical));
+        assert_eq!(strict(vec![0x00, 0x82, 0x00, 0x81]), Err(Error::NonCanonical));
+        assert_eq!(strict(vec![7, 8, 9, 0xFF]), Err(Error::NonCanonical));
+        // Max-length runs may continue; different run bytes may touch.
+        assert_eq!(strict(vec![0x00, 127, 0x00, 0]).unwrap().len(), 129);
+        assert_eq!(strict(vec![0x00, 0x9F, 0x00, 0x80]).unwrap().len(), 33);
+        assert_eq!(strict(vec![0x00, 3, 0x00, 0x80, 0x00, 0]).unwrap(), [0, 0, 0, 0, 0xFF, 0]);
+        assert_eq!(strict(vec![0x00, 3, 5, 0x00, 3]).unwrap().len(), 9);
+        assert_eq!(decompress_strict(&[1, 0xFF]), Err(Error::RunMarkerWithoutControl));
+        // Accepted exactly when the stream is its own canonical form.
+        for seed in 0..2000 {
+            let c = arb(seed);
+            let canonical = recompress(&c).map_or(false, |r| r == c);
+            assert_eq!(decompress_strict(&c).is_ok(), canonical, "seed {}", seed);
+        }
+    }
+    #[test] fn compress_slice_exact_fit() {
+        let v = [0,0,0,0, 42, 0xFF,0xFF,0xFF, 1,2,3, 0, 0xFF, 0, 0xAA,0xBB, 0, 0xFF];
+        let c = compress(&v);
+        let mut buf = vec![0u8; c.len()];
+        assert_eq!(compress_slice(&v, &mut buf), Ok(c.len()));
+        assert_eq!(buf, c);
+        let mut big = [0u8; 64];
+        assert_eq!(compress_slice(&v, &mut big), Ok(c.len()));
+        assert_eq!(&big[..c.len()], &c[..]);
+    }
+    #[test] fn compress_slice_one_short() {
+        let v = [0,0,0,0, 42, 0xFF,0xFF,0xFF, 1,2,3, 0, 0xFF, 0, 0xAA,0xBB, 0, 0xFF];
+        let n = compress(&v).len();
+        let mut buf = vec![0u8; n - 1];
+        assert_eq!(compress_slice(&v, &mut buf), Err(Error::OutputTooSmall { needed: n }));
+        assert_eq!(compress_slice(&[], &mut []), Ok(0));
+        assert_eq!(compress_slice(&[1], &mut []), Err(Error::OutputTooSmall { needed: 1 }));
+    }
+    #[test] fn decompress_slice_fits() {
+        let v = [0,0,0,0, 42, 0xFF,0xFF,0xFF, 1,2,3, 0, 0xFF, 0, 0xAA,0xBB, 0, 0xFF];
+        let c = compress(&v);
+        let mut buf = [0u8; 64];
+        assert_eq!(decompress_slice(&c, &mut buf), Ok(v.len()));
+        assert_eq!(&buf[..v.len()], &v[..]);
+        let mut exact = [0u8; 18];
+        assert_eq!(decompress_slice(&c, &mut exact), Ok(v.len()));
+        assert_eq!(exact, v);
+    }
+    #[test] fn decompress_slice_too_small() {
+        let c = compress(&[0; 300]);
+        let mut buf = [0u8; 299];
+        assert_eq!(decompress_slice(&c, &mut buf), Err(Error::OutputTooSmall { needed: 300 }));
+        let mut bad = c.clone();
+        bad.push(0x00);
+        assert_eq!(decompress_slice(&bad, &mut buf), Err(Error::RunMarkerWithoutControl));
+    }
+    #[test] fn decompressed_len_matches() {
+        let cases: [&[u8]; 6] = [
+            &[], &[1,2,3,4,5], &[0; 129], &[0xFF; 33], &[0xFF, 0, 0xFF, 0, 7],
+            &[0,0,0,0, 42, 0xFF,0xFF,0xFF, 1,2,3, 0, 0xFF, 0, 0xAA,0xBB, 0, 0xFF],
+        ];
+        for v in cases {
+            assert_eq!(decompressed_len(&compress(v)), Ok(v.len()), "in:{:x?}", v);
+        }
+        assert_eq!(decompressed_len(&[0xFF]), Err(Error::RunMarkerWithoutControl));
+        assert_eq!(decompressed_len(&[0xFF, 0xA0 ^ 0xFF]), Err(Error::InvalidRunLength { len: 33 }));
+    }
+    #[test] fn max_compressed_len_is_tight() {
+        let alt: Vec<u8> = (0..100).map(|i| if i % 2 == 0 { 0x00 } else { 0xFF }).collect();
+        assert_eq!(compress(&alt).len(), max_compressed_len(alt.len()));
+        let mut buf = [0u8; max_compressed_len(18)];
+        let v = [0,0,0,0, 42, 0xFF,0xFF,0xFF, 1,2,3, 0, 0xFF, 0, 0xAA,0xBB, 0, 0xFF];
+        assert!(compress_slice(&v, &mut buf).is_ok());
+        assert_eq!(max_compressed_len(0), 0);
+        for n in [0, 1, 17, 1 << 20] {
+            assert_eq!(max_compressed_len(n), n * WORST_CASE_NUMERATOR / WORST_CASE_DENOMINATOR);
+        }
+        assert_eq!(compress(&[0xFF]).len(), WORST_CASE_NUMERATOR / WORST_CASE_DENOMINATOR);
+    }
+    #[test] fn decompress_with_limit_stops_bombs() {
+        let c = compress(&[0; 128 * 64]); // 128 bytes -> 8 KiB
+        assert_eq!(c.len(), 128);
+        assert_eq!(decompress_with_limit(&c, 1000), Err(Error::OutputLimitExceeded { limit: 1000 }));
+        assert_eq!(decompress_with_limit(&c, 128 * 64).unwrap().len(), 128 * 64);
+        assert_eq!(decompress_with_limit(&compress(&[1, 2, 3]), 2), Err(Error::OutputLimitExceeded { limit: 2 }));
+        assert_eq!(decompress_with_limit(&compress(&[]), 0), Ok(vec![]));
+    }
+    #[test] fn repeated_max_runs_decode_in_bulk() {
+        // Full-length runs back to back, including at the header, split at every
+        // offset so the bulk path meets chunk boundaries mid-token.
+        let v = [&[0; 128 * 5][..], &[0xFF; 32 * 4], &[0; 127], &[0xFF; 32 * 3 + 1], &[9], &[0; 256]].concat();
+        let c = compress(&v);
+        assert_eq!(decompress(&c).unwrap(), v);
+        for split in 0..=c.len() {
+            let mut d = Decompressor::new();
+            d.push(&c[..split]).unwrap();
+            d.push(&c[split..]).unwrap();
+            assert_eq!(d.finish().unwrap(), v, "split at {}", split);
+        }
+        // The limit still stops at the last whole run that fits.
+        let mut out = Vec::new();
+        let mut dec = decode::Decoder::with_limit(128 * 3 + 5);
+        assert_eq!(dec.push(&c, &mut out), Err(Error::OutputLimitExceeded { limit: 128 * 3 + 5 }));
+        assert_eq!(out.len(), 128 * 3);
+    }
+    #[test] fn safety_cap_stops_bombs() {
+        // Fuzz regression: a stream of nothing but max zero runs (64x expansion).
+        let mut bomb = [0x00, 0x7F].repeat(4096);
+        for b in &mut bomb[..4] { *b ^= 0xFF; }
+        let opts = CdrleOptions::new().max_output(100_000);
+        assert_eq!(decompress_with(&bomb, &opts), Err(Error::OutputTooLarge { max: 100_000 }));
+        assert_eq!(decompress_with(&bomb, &opts.max_output(128 * 4096)).unwrap().len(), 128 * 4096);
+        assert_eq!(decompress(&bomb).unwrap().len(), 128 * 4096);
+        assert_eq!(CdrleOptions::new().max_output, DEFAULT_MAX_OUTPUT);
+        // The caller's limit replaces the cap; non-allocating decoders have none.
+        assert!(decompress_with_limit(&bomb, usize::MAX).is_ok());
+        assert_eq!(decompressed_len(&bomb), Ok(128 * 4096));
+    }
+    #[test] fn cap_checked_before_allocating() {
+        // One max zero run past the 1 GiB cap: 16 MiB of stream, rejected by the
+        // `decompressed_len` pre-pass with nothing decoded or reserved. The
+        // built-in cap reports `OutputTooLarge`, not `OutputLimitExceeded`.
+        let mut bomb = [0x00, 0x7F].repeat(DEFAULT_MAX_OUTPUT / 128 + 1);
+        for b in &mut bomb[..4] { *b ^= 0xFF; }
+        assert_eq!(decompressed_len(&bomb), Ok(DEFAULT_MAX_OUTPUT + 128));
+        assert_eq!(decompress(&bomb), Err(Error::OutputTooLarge { max: DEFAULT_MAX_OUTPUT }));
+        bomb.truncate(bomb.len() - 2);
+        assert_eq!(decompressed_len(&bomb), Ok(DEFAULT_MAX_OUTPUT)); // exactly at the cap: allowed
+    }
+    #[cfg(feature = "std")]
+    #[test] fn error_display() {
+        use alloc::string::ToString;
+        assert_eq!(Error::InvalidRunLength { len: 33 }.to_string(), "run length 33 exceeds the maximum for its run byte");
+        assert_eq!(Error::RunMarkerWithoutControl.to_string(), "run marker (0x00) without CONTROL byte at end of stream");
+        assert_eq!(Error::InvalidRunLengthAt { len: 33, offset: 9, control: 0xA0 }.to_string(),
+                   "run length 33 exceeds the maximum for its run byte (CONTROL 0xa0 at offset 9)");
+        let e: Box<dyn std::error::Error> = Box::new(Error::OutputTooSmall { needed: 7 });
+        assert_eq!(e.to_string(), "output buffer too small (7 bytes needed)");
+    }
+    #[test] fn error_display_without_alloc() {
+        /// A fixed stack buffer, as on an embedded target.
+        struct Buf { b: [u8; 64], n: usize }
+        impl core::fmt::Write for Buf {
+            fn write_str(&mut self, s: &str) -> core::fmt::Result {
+                let end = self.n + s.len();
+                self.b.get_mut(self.n..end).ok_or(core::fmt::Error)?.copy_from_slice(s.as_bytes());
+                self.n = end;
+                Ok(())
+            }
+        }
+        let mut w = Buf { b: [0; 64], n: 0 };
+        core::fmt::Write::write_fmt(&mut w, format_args!("{}", Error::InvalidRunLength { len: 100 })).unwrap();
+        assert_eq!(&w.b[..w.n], b"run length 100 exceeds the maximum for its run byte");
+    }
+    #[test] fn recoverable_errors() {
+        let mut partial = compress(&[1, 2, 3, 4, 0, 0, 0]);
+        partial.pop();
+        assert!(decompress(&partial).unwrap_err().is_recoverable());
+        assert!(!decompress(&[0xFF, 0xA0 ^ 0xFF]).unwrap_err().is_recoverable());
+        assert!(!decompress_exact(&compress(&[1]), 2).unwrap_err().is_recoverable());
+        assert!(Error::OutputTooSmall { needed: 4 }.is_recoverable());
+        assert!(Error::OutputTooLarge { max: 4 }.is_recoverable());
+        assert!(!Error::ChecksumMismatch { expected: 1, found: 2 }.is_recoverable());
+        assert!(!Error::UnknownFormat.is_recoverable());
+    }
+    /// Deterministic xorshift64 bytes, biased toward 0x00/0xFF runs.
+    fn noise(seed: u64, n: usize) -> Vec<u8> {
+        let mut x = seed | 1;
+        let mut v = Vec::with_capacity(n);
+        while v.len() < n {
+            x ^= x << 13; x ^= x >> 7; x ^= x << 17;
+            let (b, len) = match x % 4 { 0 => (0x00, x >> 8 & 0xFF), 1 => (0xFF, x >> 8 & 0x3F), _ => (x as u8 >> 1, 1) };
+            v.extend(core::iter::repeat(b).take(len as usize + 1));
+        }
+        v.truncate(n);
+        v
+    }
+
+    /// Property-test inputs: deterministic xorshift64 mixes of runs straddling
+    /// the 128/32 split points, alternating 0x00/0xFF, and literal noise.
+    fn arb(seed: u64) -> Vec<u8> {
+        let mut x = seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1;
+        let mut next = move || { x ^= x << 13; x ^= x >> 7; x ^= x << 17; x };
+        let mut v = Vec::new();
+        for _ in 0..next() % 12 {
+            let r = next();
+            let jitter = (r >> 8) as usize % 5; // -2..=2 around a boundary
+            match r % 6 {
+                0 => v.resize(v.len() + (128 * (1 + (r >> 16) as usize % 3) + jitter).saturating_sub(2), 0x00),
+                1 => v.resize(v.len() + (32 * (1 + (r >> 16) as usize % 3) + jitter).saturating_sub(2), 0xFF),
+                2 => v.extend((0..(r >> 16) as usize % 64).map(|i| if i % 2 == 0 { 0x00 } else { 0xFF })),
+                3 => v.extend((0..(r >> 16) as usize % 32).map(|i| [0x00, 0x00, 0xFF, 0x01, 0xFE][i % 5])),
+                4 => { let n = (r >> 16) as usize % 40; v.extend((0..n).map(|_| next() as u8)); }
+                _ => v.extend_from_slice(&noise(r, (r >> 16) as usize % 300)),
+            }
+        }
+        v
+    }
+
+    #[test] fn prop_structural_invariants() {
+        for seed in 0..2000 {
+            let x = arb(seed);
+            let c = compress(&x);
+            assert_eq!(decompress(&c).as_deref(), Ok(&x[..]), "seed {}", seed);
+            assert_eq!(compressed_len(&x), c.len(), "seed {}", seed);
+            assert_eq!(decompressed_len(&c), Ok(x.len()), "seed {}", seed);
+            assert!(c.len() <= max_compressed_len(x.len()), "seed {}", seed);
+        }
+    }
+    #[test] fn prop_decoders_agree_on_garbage() {
+        for seed in 0..2000 {
+            let c = arb(seed); // arbitrary bytes, read as a compressed stream
+            let d = decompress(&c);
+            assert_eq!(decompressed_len(&c), d.as_ref().map(Vec::len).map_err(|e| *e), "seed {}", seed);
+            assert_eq!(validate(&c), d.map(drop), "seed {}", seed);
+        }
+    }
+    #[test] fn compress_from_iter_matches() {
+        for seed in 0..64 {
+            let v = noise(seed, seed as usize * 37);
+            assert_eq!(compress_from_iter(v.iter().copied()), compress(&v), "seed {}", seed);
+            // An iterator without a useful size hint.
+            assert_eq!(compress_from_iter(v.iter().copied().filter(|_| true)), compress(&v));
+        }
+    }
+    #[test] fn options_default_matches_compress() {
+        let v = [0,0,0,0, 42, 0xFF,0xFF,0xFF, 1,2,3, 0, 0xFF, 0, 0xAA,0xBB, 0, 0xFF];
+        let opts = CdrleOptions::default();
+        assert_eq!(compress_with(&v, &opts), compress(&v));
+        assert_eq!(decompress_with(&compress(&v), &opts).unwrap(), v);
+    }
+    #[test] fn options_without_negation() {
+        let v = [0,0,0,0, 42, 0xFF,0xFF,0xFF, 1,2,3, 0, 0xFF, 0, 0xAA,0xBB, 0, 0xFF];
+        let opts = CdrleOptions::new().negate_header(false);
+        let c = compress_with(&v, &opts);
+        assert_eq!(&c[..4], &[0x00, 0x03, 42, 0x00]);
+        let mut negated = c.clone();
+        for b in negated.iter_mut().take(4) { *b ^= 0xFF; }
+        assert_eq!(negated, compress(&v));
+        assert_eq!(decompress_with(&c, &opts).unwrap(), v);
+    }
+    #[test] fn options_negate_prefix_len() {
+        let v = [0,0,0,0, 42, 0xFF,0xFF,0xFF, 1,2,3, 0, 0xFF, 0, 0xAA,0xBB, 0, 0xFF];
+        let plain = compress_with(&v, &CdrleOptions::new().negate_header(false));
+        for n in [0, 2, 4, 8, 100] {
+            let opts = CdrleOptions::new().negate_prefix_len(n);
+            let c = compress_with(&v, &opts);
+            for (i, (&a, &b)) in c.iter().zip(&plain).enumerate() {
+                assert_eq!(a, if i < n { b ^ 0xFF } else { b }, "n {} at {}", n, i);
+            }
+            assert_eq!(decompress_with(&c, &opts).unwrap(), v, "n {}", n);
+            assert_eq!(decompress_with(&compress_with(&[1], &opts), &opts).unwrap(), [1]);
+        }
+        assert_eq!(CdrleOptions::new().negate_prefix_len(4), CdrleOptions::new());
+        assert_eq!(CdrleOptions::new().negate_prefix_len(0), CdrleOptions::new().negate_header(false));
+    }
+    #[test] fn plain_has_no_negated_bytes() {
+        for seed in 0..32 {
+            let x = arb(seed);
+            let c = compress_plain(&x);
+            assert_eq!(decompress_plain(&c).unwrap(), x);
+            let mut neg = compress(&x);
+            negate_header(&mut neg);
+            assert_eq!(c, neg);
+        }
+        assert_eq!(compress_plain(&[0, 0, 7, 0xFF]), [0x00, 0x01, 7, 0x00, 0x80]);
+        assert_eq!(decompress_plain(&[7, 0x00]), Err(Error::RunMarkerWithoutControl));
+    }
+    #[test] fn cfg_default_matches_compress() {
+        for seed in 0..16 {
+            let v = noise(seed, 500);
+            assert_eq!(compress_cfg(&v, &RleConfig::new()), compress(&v));
+        }
+    }
+    #[test] fn cfg_custom_run_bytes() {
+        let cfg = RleConfig::new().run_bytes(0x20, 0x55);
+        let mut v = b"a   b".to_vec();
+        v.extend_from_slice(&[0x20; 200]);
+        v.extend_from_slice(&[0x55; 40]);
+        v.extend_from_slice(&[0x00, 0x00, 0xFF, 0x55, 0x20, 7]);
+        let c = compress_cfg(&v, &cfg);
+        assert!(c.len() < 30, "{:x?}", c);
+        assert_eq!(decompress_cfg(&c, &cfg).unwrap(), v);
+        for seed in 0..16 {
+            let v = noise(seed, 500);
+            assert_eq!(decompress_cfg(&compress_cfg(&v, &cfg), &cfg).unwrap(), v);
+        }
+        // 0x55 runs keep the 32 cap of the second slot.
+        let bad = [0x20 ^ 0xFF, 0xA0 ^ 0xFF];
+        assert_eq!(decompress_cfg(&bad, &cfg), Err(Error::InvalidRunLength { len: 33 }));
+    }
+    #[test] fn cfg_max_runs() {
+        let mut v = vec![0u8; 1000];
+        v.extend_from_slice(&[0xFF; 300]);
+        v.extend_from_slice(&[1, 0, 0xFF, 0, 2]);
+        for (z, f) in [(128, 128), (16, 100), (1, 1), (64, 32)] {
+            let cfg = RleConfig::new().max_runs(z, f);
+            let c = compress_cfg(&v, &cfg);
+            assert_eq!(decompress_cfg(&c, &cfg).unwrap(), v, "max_runs({}, {})", z, f);
+        }
+        let wide = RleConfig::new().max_runs(128, 128);
+        assert!(compress_cfg(&v, &wide).len() < compress(&v).len());
+        // FF run of 100 is valid for `wide`, not for the default.
+        let c = [0xFF, 0xE3 ^ 0xFF];
+        assert_eq!(decompress_cfg(&c, &wide).unwrap(), [0xFF; 100]);
+        assert_eq!(decompress(&c), Err(Error::InvalidRunLength { len: 100 }));
+        // Zero runs are bounded too once their max drops below 128.
+        let c = [0xFF, 0x10 ^ 0xFF];
+        assert_eq!(decompress_cfg(&c, &RleConfig::new().max_runs(16, 32)), Err(Error::InvalidRunLength { len: 17 }));
+    }
+    #[test] fn generic_caps_match_cfg() {
+        let mut v = vec![0u8; 1000];
+        v.extend_from_slice(&[0xFF; 300]);
+        v.extend_from_slice(&[1, 0, 0xFF, 0, 2]);
+        assert_eq!(compress_g::<128, 32>(&v), compress(&v));
+        assert_eq!(decompress_g::<128, 32>(&compress(&v)), decompress(&compress(&v)));
+        let c = compress_g::<16, 100>(&v);
+        assert_eq!(c, compress_cfg(&v, &RleConfig::new().max_runs(16, 100)));
+        assert_eq!(decompress_g::<16, 100>(&c).unwrap(), v);
+        assert_eq!(decompress_g::<128, 32>(&c), Err(Error::InvalidRunLength { len: 100 }));
+        assert_eq!(decompress_g::<1, 1>(&compress_g::<1, 1>(&v)).unwrap(), v);
+    }
+    #[test] #[should_panic] fn cfg_max_runs_out_of_range() {
+        let _ = RleConfig::new().max_runs(129, 32);
+    }
+    #[test] fn verify_roundtrip_holds() {
+        assert!(verify_roundtrip(&[]));
+        for seed in 0..16 { assert!(verify_roundtrip(&noise(seed, 700))); }
+    }
+    #[test] fn compress_in_place_matches() {
+        let alt: Vec<u8> = (0..100).map(|i| if i % 2 == 0 { 0x00 } else { 0xFF }).collect();
+        let mut tail_alt = vec![7u8; 50];
+        tail_alt.extend_from_slice(&alt);
+        let mut cases = vec![Vec::new(), vec![0], vec![0xFF, 0], alt, tail_alt];
+        cases.extend((0..32).map(|seed| noise(seed, 400)));
+        for v in cases {
+            let mut buf = v.clone();
+            compress_in_place(&mut buf);
+            assert_eq!(buf, compress(&v), "in:{:x?}", v);
+        }
+        let mut buf = vec![0u8; 1000];
+        let ptr = buf.as_ptr();
+        compress_in_place(&mut buf);
+        assert_eq!(buf, compress(&[0; 1000]));
+        assert_eq!(buf.as_ptr(), ptr);
+    }
+    #[test] fn compress_in_place_drains_early_overtake() {
+        // Lone 0x00 up front overtakes the input by one byte; the 1 MiB literal
+        // body behind it must not all be queued.
+        let mut v = vec![0x00, 0x01];
+        v.extend((0..1 << 20).map(|i| (i % 254 + 1) as u8));
+        let expected = compress(&v);
+        let mut buf = v.clone();
+        let (w, spill) = compress_in_place_raw(&mut buf);
+        assert_eq!((w, spill.len()), (v.len(), 1));
+        assert!(spill.capacity() < 64, "spill grew to {}", spill.capacity());
+        let mut buf = v.clone();
+        compress_in_place(&mut buf);
+        assert!(buf == expected);
+        assert!(compress_owned(v) == expected);
+    }
+    #[test] fn sink_impls() {
+        let v = [0,0,0,0, 42, 0xFF,0xFF,0xFF, 1,2,3, 0, 0xFF, 0, 0xAA,0xBB, 0, 0xFF];
+        let c = compress(&v);
+        let mut buf = [0u8; 32];
+        let mut rest = &mut buf[..];
+        compress_to_sink(&v, &mut rest);
+        let left = rest.len();
+        assert_eq!(&buf[..32 - left], &c[..]);
+
+        /// A sink that only hashes what it is given.
+        struct Fnv(u64);
+        impl Sink for Fnv {
+            fn push_byte(&mut self, b: u8) { self.0 = (self.0 ^ b as u64).wrapping_mul(0x100_0000_01b3); }
+        }
+        let (mut a, mut b) = (Fnv(0xcbf2_9ce4_8422_2325), Fnv(0xcbf2_9ce4_8422_2325));
+        compress_to_sink(&v, &mut a);
+        b.push_slice(&c);
+        assert_eq!(a.0, b.0);
+    }
+    #[test] #[should_panic] fn slice_sink_full() {
+        let mut buf = [0u8; 2];
+        compress_to_sink(&[1, 2, 3], &mut &mut buf[..]);
+    }
     #[test] fn err_run_marker_without_control() {
         let mut c = vec![0x00];
         for i in 0..c.len().min(4) { c[i] ^= 0xFF; }
@@ -140,4 +1568,33 @@ mod tests {
         for i in 0..c.len().min(4) { c[i] ^= 0xFF; }
         assert_eq!(decompress(&c), Err(Error::InvalidRunLength { len: 33 }));
     }
+    #[test] fn control_byte_helpers() {
+        assert_eq!(decode_control(0xA0), (true, 33));
+        assert_eq!(encode_control(true, 33), 0xA0);
+        assert_eq!((encode_control(false, 1), encode_control(false, 128), encode_control(true, 32)), (0x00, 0x7F, 0x9F));
+        for c in 0..=255u8 {
+            let (is_ff, len) = decode_control(c);
+            assert_eq!(encode_control(is_ff, len), c);
+        }
+    }
+    #[test] fn apply_run_matches_decompress() {
+        // A minimal decoder built from the public helpers.
+        let v = noise(7, 2000);
+        let c = compress(&v);
+        let mut out = Vec::new();
+        let mut bytes = c.iter().enumerate().map(|(i, &b)| if i < 4 { b ^ 0xFF } else { b });
+        while let Some(b) = bytes.next() {
+            if b != 0x00 { out.push(b); continue; }
+            let (is_ff, len) = decode_control(bytes.next().unwrap());
+            apply_run(&mut out, is_ff, len).unwrap();
+        }
+        assert_eq!(out, v);
+        assert_eq!(apply_run(&mut out, true, 33), Err(Error::InvalidRunLength { len: 33 }));
+        assert_eq!(apply_run(&mut out, false, 129), Err(Error::InvalidRunLength { len: 129 }));
+        assert_eq!(apply_run(&mut out, false, 0), Err(Error::InvalidRunLength { len: 0 }));
+        assert_eq!(out.len(), v.len());
+    }
+    #[test] #[should_panic] fn encode_control_rejects_zero() {
+        let _ = encode_control(false, 0);
+    }
 }
@@ -0,0 +1,110 @@
+//! Format options for the `*_with` and `*_cfg` entry points.
+
+use crate::{DEFAULT_MAX_OUTPUT, MAX_FF_RUN, MAX_ZERO_RUN};
+
+/// Stream format options. The default is exactly the format of
+/// [`compress`](crate::compress)/[`decompress`](crate::decompress).
+///
+/// A stream must be decoded with the same options it was encoded with; the
+/// format carries no header recording them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CdrleOptions {
+    pub(crate) negate_prefix_len: usize,
+    pub(crate) max_output: usize,
+}
+
+impl Default for CdrleOptions {
+    fn default() -> Self { Self::new() }
+}
+
+impl CdrleOptions {
+    pub const fn new() -> Self {
+        Self { negate_prefix_len: 4, max_output: DEFAULT_MAX_OUTPUT }
+    }
+
+    /// Whether the first 4 bytes of the compressed stream are XOR-negated
+    /// (default `true`). Disable for interop with plain RLE tooling.
+    pub const fn negate_header(self, yes: bool) -> Self {
+        self.negate_prefix_len(if yes { 4 } else { 0 })
+    }
+
+    /// How many leading bytes of the compressed stream are XOR-negated (default
+    /// 4; 0 is `negate_header(false)`). The encoder negates `min(n, len)` bytes of
+    /// its output and the decoder un-negates stream positions `< n`.
+    pub const fn negate_prefix_len(mut self, n: usize) -> Self {
+        self.negate_prefix_len = n;
+        self
+    }
+
+    /// Safety cap on the decoded size (default `DEFAULT_MAX_OUTPUT`); decoding
+    /// fails with `OutputTooLarge` instead of growing the output past it. Not
+    /// part of the format: only `decompress_with` reads it.
+    pub const fn max_output(mut self, max: usize) -> Self {
+        self.max_output = max;
+        self
+    }
+}
+
+/// Which two byte values are run-encoded. The default, `{0x00, 0xFF}`, is the
+/// standard format.
+///
+/// The CONTROL layout is unchanged: bit7 selects the run byte, the low 7 bits
+/// hold `len - 1`. The first run byte (bit7 = 0, runs of 1..=128 by default)
+/// doubles as the run marker, so it never appears as a literal; the second takes
+/// runs of 1..=32 by default. Every other byte, 0x00 included when not chosen,
+/// is a literal.
+///
+/// Each run byte has its own 7-bit length field, so their maxima are
+/// independent: raising one never costs the other any range. Both are capped
+/// at 128 (`0x7F + 1`).
+///
+/// A stream must be decoded with the same config it was encoded with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RleConfig {
+    pub(crate) bytes: [u8; 2],
+    pub(crate) max: [usize; 2],
+}
+
+impl Default for RleConfig {
+    fn default() -> Self { Self::new() }
+}
+
+impl RleConfig {
+    pub const fn new() -> Self {
+        Self { bytes: [0x00, 0xFF], max: [MAX_ZERO_RUN, MAX_FF_RUN] }
+    }
+
+    /// Sets the run bytes: `marker` is also the run marker, `other` is the
+    /// second run byte.
+    ///
+    /// Panics if `marker == other`.
+    pub const fn run_bytes(mut self, marker: u8, other: u8) -> Self {
+        assert!(marker != other, "run bytes must differ");
+        self.bytes = [marker, other];
+        self
+    }
+
+    /// Sets the longest run of each run byte. Longer runs are split; the decoder
+    /// rejects longer lengths with `InvalidRunLength`.
+    ///
+    /// Panics unless both are in `1..=128`.
+    pub const fn max_runs(mut self, marker: usize, other: usize) -> Self {
+        assert!(marker >= 1 && marker <= 128 && other >= 1 && other <= 128, "max run must be in 1..=128");
+        self.max = [marker, other];
+        self
+    }
+}
+
+/// Compile-time run caps for `compress_g`/`decompress_g`. Naming `CFG` for caps
+/// that do not fit the 7-bit length field fails the build, not the call.
+#[cfg(feature = "alloc")]
+pub(crate) struct Caps<const Z: usize, const F: usize>;
+
+#[cfg(feature = "alloc")]
+impl<const Z: usize, const F: usize> Caps<Z, F> {
+    pub(crate) const CFG: RleConfig = {
+        assert!(Z >= 1 && Z <= 128, "Z must be in 1..=128 to fit the 7-bit CONTROL length");
+        assert!(F >= 1 && F <= 128, "F must be in 1..=128 to fit the 7-bit CONTROL length");
+        RleConfig::new().max_runs(Z, F)
+    };
+}
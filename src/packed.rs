@@ -0,0 +1,121 @@
+//! Opt-in packed format: a short 0x00 run and the 0xFF run right after it share
+//! one `(0x00, CONTROL)` pair.
+//!
+//! v1 leaves CONTROL bytes `0xA0..=0xFF` unused (0xFF runs stop at 32). Here they
+//! mean "`z` zeros then `f` 0xFF bytes", `CONTROL = 0xA0 + (z - 1) * 8 + (f - 1)`,
+//! for `z` in `1..=12` and `f` in `1..=8`. Everything else, header negation
+//! included, is as in v1, so interleaved short runs such as `00 FF 00 FF ..` cost
+//! 1 output byte per input byte instead of 2.
+//!
+//! `decompress_packed` decodes any v1 stream too; `decompress` rejects packed
+//! pairs with `InvalidRunLength`.
+
+use alloc::vec::Vec;
+
+use crate::encode::negate_header;
+use crate::{runs, Error, Run, DEFAULT_MAX_OUTPUT};
+
+const PACKED: u8 = 0xA0;
+const PACK_MAX_ZEROS: usize = 12;
+const PACK_MAX_FFS: usize = 8;
+
+/// Compresses `input` to the packed format. Decode with `decompress_packed`.
+///
+/// Segments exactly as `compress` does, then merges each zero run of at most 12
+/// that is directly followed by an 0xFF run of at most 8, so the output is never
+/// longer than `compress(input)`.
+pub fn compress_packed(input: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(input.len());
+    let mut segs = runs(input).peekable();
+    while let Some(seg) = segs.next() {
+        match seg {
+            Run::Literal(s) => out.extend_from_slice(s),
+            Run::Zeros(z) => match segs.peek() {
+                Some(&Run::Ffs(f)) if z <= PACK_MAX_ZEROS && f <= PACK_MAX_FFS => {
+                    segs.next();
+                    out.extend_from_slice(&[0x00, PACKED + ((z - 1) * PACK_MAX_FFS + (f - 1)) as u8]);
+                }
+                _ => out.extend_from_slice(&[0x00, (z - 1) as u8]),
+            },
+            Run::Ffs(f) => out.extend_from_slice(&[0x00, 0x80 | (f - 1) as u8]),
+        }
+    }
+    negate_header(&mut out);
+    out
+}
+
+/// Decompresses `comp` produced by `compress_packed` (or `compress`).
+///
+/// Errors:
+/// - RunMarkerWithoutControl  (0x00 as final byte)
+/// - OutputTooLarge{max}      (decoded size would pass `DEFAULT_MAX_OUTPUT`)
+pub fn decompress_packed(comp: &[u8]) -> Result<Vec<u8>, Error> {
+    let mut out = Vec::with_capacity(comp.len());
+    let mut bytes = comp.iter().enumerate().map(|(i, &b)| if i < 4 { b ^ 0xFF } else { b });
+    while let Some(b) = bytes.next() {
+        if b != 0x00 {
+            out.push(b);
+            continue;
+        }
+        let c = bytes.next().ok_or(Error::RunMarkerWithoutControl)?;
+        let (zeros, ffs) = match c {
+            0x00..=0x7F => (c as usize + 1, 0),
+            0x80..=0x9F => (0, (c & 0x7F) as usize + 1),
+            _ => {
+                let k = (c - PACKED) as usize;
+                (k / PACK_MAX_FFS + 1, k % PACK_MAX_FFS + 1)
+            }
+        };
+        if zeros + ffs > DEFAULT_MAX_OUTPUT - out.len() {
+            return Err(Error::OutputTooLarge { max: DEFAULT_MAX_OUTPUT });
+        }
+        out.resize(out.len() + zeros, 0x00);
+        out.resize(out.len() + ffs, 0xFF);
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compress;
+    use alloc::vec;
+
+    fn rt(v: &[u8]) {
+        let c = compress_packed(v);
+        assert_eq!(decompress_packed(&c).unwrap(), v, "in:{:x?} cmp:{:x?}", v, c);
+        assert!(c.len() <= compress(v).len());
+    }
+
+    #[test] fn packed_roundtrip() {
+        rt(&[]);
+        rt(&[0, 0xFF]);
+        rt(&[0,0,0,0, 42, 0xFF,0xFF,0xFF, 1,2,3, 0, 0xFF, 0, 0xAA,0xBB, 0, 0xFF]);
+        for z in 1..=14 {
+            for f in 1..=10 {
+                let mut v = vec![0x00; z];
+                v.resize(z + f, 0xFF);
+                v.push(7);
+                rt(&v);
+                rt(&v[..z + f]);
+            }
+        }
+        let mut long = vec![0u8; 300];
+        long.extend_from_slice(&[0xFF; 40]);
+        rt(&long);
+    }
+    #[test] fn packed_halves_alternating() {
+        let alt: Vec<u8> = (0..1000).map(|i| if i % 2 == 0 { 0x00 } else { 0xFF }).collect();
+        assert_eq!(compress(&alt).len(), 2000);
+        assert_eq!(compress_packed(&alt).len(), 1000);
+        assert_eq!(decompress_packed(&compress_packed(&alt)).unwrap(), alt);
+        // Every CONTROL byte is meaningful, so only a dangling marker is an error.
+        assert_eq!(decompress_packed(&[1 ^ 0xFF, 0xFF]), Err(Error::RunMarkerWithoutControl));
+        assert_eq!(decompress_packed(&[0xFF, 0xFF ^ 0xFF]).unwrap(), [vec![0x00; 12], vec![0xFF; 8]].concat());
+        assert_eq!(crate::decompress(&compress_packed(&alt)), Err(Error::InvalidRunLength { len: 33 }));
+    }
+    #[test] fn packed_reads_v1() {
+        let v = [0,0,0,0, 42, 0xFF,0xFF,0xFF, 1,2,3, 0, 0xFF, 0, 0xAA,0xBB, 0, 0xFF];
+        assert_eq!(decompress_packed(&compress(&v)).unwrap(), v);
+    }
+}
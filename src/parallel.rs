@@ -0,0 +1,77 @@
+//! Multi-threaded block compression.
+
+use std::thread;
+use std::vec::Vec;
+
+use crate::frame::{put_frame, split_frames};
+use crate::{compress, decompress, Error};
+
+/// Maps `f` over `items` on up to `available_parallelism()` scoped threads,
+/// preserving order.
+fn par_map<T: Sync, R: Send>(items: &[T], f: impl Fn(&T) -> R + Sync) -> Vec<R> {
+    let threads = thread::available_parallelism().map_or(1, |n| n.get());
+    let per = ((items.len() + threads - 1) / threads).max(1);
+    let f = &f;
+    thread::scope(|s| {
+        let handles: Vec<_> = items
+            .chunks(per)
+            .map(|c| s.spawn(move || c.iter().map(f).collect::<Vec<_>>()))
+            .collect();
+        handles.into_iter().flat_map(|h| h.join().unwrap()).collect()
+    })
+}
+
+/// Splits `input` into `block_size`-byte blocks, compresses them in parallel,
+/// and concatenates them as length-prefixed frames.
+///
+/// Runs never span blocks, so every block is a self-contained `compress`
+/// stream (with its own header negation). The decoder needs only the frame
+/// lengths, not `block_size`. The output is *not* a plain `compress` stream;
+/// decode it with `decompress_parallel`.
+///
+/// Panics if `block_size` is 0.
+pub fn compress_parallel(input: &[u8], block_size: usize) -> Vec<u8> {
+    assert!(block_size != 0, "block_size must be nonzero");
+    let blocks: Vec<&[u8]> = input.chunks(block_size).collect();
+    let mut out = Vec::with_capacity(input.len() + blocks.len() * 2);
+    for c in par_map(&blocks, |b| compress(b)) {
+        put_frame(&mut out, &c);
+    }
+    out
+}
+
+/// Decodes the output of `compress_parallel`, decompressing frames in parallel.
+///
+/// Errors: as `decompress`, plus
+/// - TruncatedFrame  (a frame runs past the end of `comp`)
+pub fn decompress_parallel(comp: &[u8]) -> Result<Vec<u8>, Error> {
+    let frames = split_frames(comp)?;
+    let mut out = Vec::new();
+    for d in par_map(&frames, |f| decompress(f)) {
+        out.extend_from_slice(&d?);
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test] fn parallel_roundtrip() {
+        let mut v = vec![0u8; 10_000];
+        for i in (0..v.len()).step_by(7) { v[i] = (i % 251) as u8; }
+        v.extend_from_slice(&[0xFF; 3000]);
+        for bs in [1, 100, 4096, v.len(), v.len() + 1] {
+            let c = compress_parallel(&v, bs);
+            assert_eq!(decompress_parallel(&c).unwrap(), v, "block size {}", bs);
+        }
+        assert_eq!(compress_parallel(&[], 16), []);
+        assert_eq!(decompress_parallel(&[]).unwrap(), []);
+    }
+    #[test] fn parallel_errors() {
+        let mut c = compress_parallel(&[1, 2, 3, 4, 5], 2);
+        c.pop();
+        assert_eq!(decompress_parallel(&c), Err(Error::TruncatedFrame));
+        assert_eq!(decompress_parallel(&[1, 0xFF]), Err(Error::RunMarkerWithoutControl));
+    }
+}
@@ -0,0 +1,100 @@
+//! One-shot entry points that report progress to a callback.
+
+use alloc::vec::Vec;
+
+use crate::decode::Decoder;
+use crate::encode::Encoder;
+use crate::sink::Negate;
+use crate::Error;
+
+/// Progress is reported at most once per this many input bytes.
+const STEP: usize = 64 * 1024;
+
+/// Like `compress`, but calls `f` with the number of input bytes consumed so far:
+/// after every 64 KiB of input, and once more with `input.len()` when done (so at
+/// least once, even for empty input). Values strictly increase, apart from that
+/// last call repeating the previous one when `input.len()` is a multiple of 64 KiB.
+pub fn compress_with_progress<F: FnMut(usize)>(input: &[u8], mut f: F) -> Vec<u8> {
+    let mut out = Vec::with_capacity(input.len());
+    let mut sink = Negate { inner: &mut out, left: 4 };
+    let mut enc = Encoder::default();
+    let mut done = 0;
+    for chunk in input.chunks(STEP) {
+        enc.push(chunk, &mut sink);
+        done += chunk.len();
+        if chunk.len() == STEP { f(done); }
+    }
+    enc.flush(&mut sink);
+    f(done);
+    out
+}
+
+/// Like `decompress`, but calls `f` with the number of bytes decoded so far.
+///
+/// Progress is checked between runs and literals, about every 256 compressed
+/// bytes; `f` is called whenever another 64 KiB of output has been produced since
+/// its last call, and once more at the end, whether the stream decoded or failed,
+/// with everything decoded before the failure. So it is called at least once.
+///
+/// Errors: as `decompress`.
+pub fn decompress_with_progress<F: FnMut(usize)>(comp: &[u8], mut f: F) -> Result<Vec<u8>, Error> {
+    let mut out = Vec::with_capacity(comp.len());
+    let mut dec = Decoder::default();
+    let mut next = STEP;
+    // 256 compressed bytes decode to at most 128 * 128 = 16 KiB < STEP.
+    for chunk in comp.chunks(256) {
+        if let Err(e) = dec.push(chunk, &mut out) {
+            f(out.len());
+            return Err(e);
+        }
+        if out.len() >= next {
+            f(out.len());
+            next = out.len() + STEP;
+        }
+    }
+    f(out.len());
+    dec.finish()?;
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{compress, decompress};
+    use alloc::vec;
+
+    #[test] fn compress_progress_is_batched() {
+        let mut v = vec![0u8; 3 * STEP + 10];
+        v[STEP] = 1;
+        let mut calls = Vec::new();
+        assert_eq!(compress_with_progress(&v, |n| calls.push(n)), compress(&v));
+        assert_eq!(calls, [STEP, 2 * STEP, 3 * STEP, 3 * STEP + 10]);
+
+        let mut calls = Vec::new();
+        assert_eq!(compress_with_progress(&[], |n| calls.push(n)), compress(&[]));
+        assert_eq!(calls, [0]);
+        let mut calls = Vec::new();
+        compress_with_progress(&v[..STEP], |n| calls.push(n));
+        assert_eq!(calls, [STEP, STEP]);
+    }
+    #[test] fn decompress_progress_is_batched() {
+        let v = vec![0u8; 10 * STEP + 5];
+        let c = compress(&v);
+        let mut calls = Vec::new();
+        assert_eq!(decompress_with_progress(&c, |n| calls.push(n)).unwrap(), v);
+        assert_eq!(calls.last(), Some(&v.len()));
+        assert!(calls.len() >= 10 && calls.len() <= 11, "{:?}", calls);
+        assert!(calls.windows(2).all(|w| w[1] - w[0] >= STEP || w[1] == v.len()));
+
+        let mut bad = compress(&[7; 500]);
+        bad.push(0x00); // dangling marker
+        let mut calls = Vec::new();
+        assert_eq!(decompress_with_progress(&bad, |n| calls.push(n)), decompress(&bad));
+        assert_eq!(calls, [500]);
+        let mut calls = Vec::new();
+        let mut bad = compress(&[3, 4, 5, 6]);
+        bad.extend_from_slice(&[0x00, 0xA0]); // FF-run of 33
+        assert_eq!(decompress_with_progress(&bad, |n| calls.push(n)), Err(Error::InvalidRunLength { len: 33 }));
+        assert_eq!(calls, [4]);
+    }
+}
@@ -0,0 +1,80 @@
+//! Word-at-a-time byte scanning for the encoder hot loop.
+
+const W: usize = 8;
+
+#[inline]
+fn word(s: &[u8], i: usize) -> u64 {
+    let mut w = [0u8; W];
+    w.copy_from_slice(&s[i..i + W]);
+    u64::from_le_bytes(w)
+}
+
+/// Length of the prefix of `s` consisting only of `b`.
+#[inline]
+pub(crate) fn run_len(s: &[u8], b: u8) -> usize {
+    let splat = u64::from_le_bytes([b; W]);
+    let mut i = 0;
+    while i + W <= s.len() {
+        let x = word(s, i) ^ splat;
+        if x != 0 {
+            return i + (x.trailing_zeros() / 8) as usize;
+        }
+        i += W;
+    }
+    while i < s.len() && s[i] == b { i += 1; }
+    i
+}
+
+/// Length of the prefix of `s` containing neither `a` nor `b` (a `memchr2`).
+#[inline]
+pub(crate) fn literal_len(s: &[u8], a: u8, b: u8) -> usize {
+    const LO: u64 = u64::from_le_bytes([0x01; W]);
+    const HI: u64 = u64::from_le_bytes([0x80; W]);
+    // High bit set in each zero byte of `x`; the lowest set bit is always exact.
+    let zeros = |x: u64| x.wrapping_sub(LO) & !x & HI;
+    let (sa, sb) = (u64::from_le_bytes([a; W]), u64::from_le_bytes([b; W]));
+    let mut i = 0;
+    while i + W <= s.len() {
+        let x = word(s, i);
+        let m = zeros(x ^ sa) | zeros(x ^ sb);
+        if m != 0 {
+            return i + (m.trailing_zeros() / 8) as usize;
+        }
+        i += W;
+    }
+    while i < s.len() && s[i] != a && s[i] != b { i += 1; }
+    i
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test] fn run_len_all_offsets() {
+        let mut buf = [0u8; 40];
+        for n in 0..buf.len() {
+            buf.fill(0xFF);
+            buf[n] = 7;
+            assert_eq!(run_len(&buf, 0xFF), n);
+            assert_eq!(run_len(&buf[..n], 0xFF), n);
+            assert_eq!(run_len(&buf[n..], 0xFF), 0);
+        }
+        assert_eq!(run_len(&[], 0), 0);
+    }
+    #[test] fn literal_len_all_offsets() {
+        let mut buf = [0u8; 40];
+        for n in 0..buf.len() {
+            for stop in [0x00, 0xFF] {
+                // 0x01 and 0x80/0xFE sit next to the stop bytes, where borrow tricks misfire.
+                for (i, p) in buf.iter_mut().enumerate() { *p = [0x01, 0x80, 0xFE, 0x7F][i % 4]; }
+                buf[n] = stop;
+                assert_eq!(literal_len(&buf, 0x00, 0xFF), n);
+                assert_eq!(literal_len(&buf[..n], 0x00, 0xFF), n);
+                if n + 1 < buf.len() { buf[n + 1] = !stop; } // the other stop byte
+                assert_eq!(literal_len(&buf, 0x00, 0xFF), n);
+            }
+        }
+        assert_eq!(literal_len(&[], 0x00, 0xFF), 0);
+        assert_eq!(literal_len(&[5, 6, 7, 8, 9, 10, 11, 12, 13, 3], 3, 0), 9);
+    }
+}
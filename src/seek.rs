@@ -0,0 +1,188 @@
+//! Random access into the decompressed data.
+
+use alloc::vec::Vec;
+
+use crate::iter::{DecodeTokens, Token};
+use crate::{decode_tokens, Error};
+
+/// Checkpoints for repeated random access into one compressed stream; see [`build_index`].
+///
+/// Entry `k` holds the compressed offset and decoded position of the token
+/// holding decoded byte `k * stride`. Tokens are 1 or 2 bytes and the header
+/// negation follows the absolute stream position, so a compressed offset alone
+/// is enough to resume decoding there.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DecodeIndex {
+    stride: usize,
+    points: Vec<(usize, usize)>, // (compressed offset, decoded position)
+    decoded_len: usize,
+}
+
+/// Walks all of `comp` once, recording a checkpoint every `stride` decoded bytes.
+/// The index takes `O(decompressed_len / stride)` space; seeks then decode at
+/// most one checkpoint gap (about `stride` bytes plus one run) before the window.
+///
+/// Panics if `stride == 0`.
+///
+/// Errors: as `decompress` (the whole stream is validated).
+pub fn build_index(comp: &[u8], stride: usize) -> Result<DecodeIndex, Error> {
+    assert!(stride != 0, "stride must be non-zero");
+    let mut toks = decode_tokens(comp);
+    let mut points = Vec::new();
+    let (mut pos, mut mark) = (0usize, 0usize);
+    loop {
+        let at = toks.offset();
+        let n = match toks.next() {
+            None => break,
+            Some(t) => match t? {
+                Token::Literal(_) => 1,
+                Token::ZeroRun(n) | Token::FfRun(n) => n,
+            },
+        };
+        while mark < pos + n {
+            points.push((at, pos));
+            mark = mark.saturating_add(stride);
+        }
+        pos += n;
+    }
+    Ok(DecodeIndex { stride, points, decoded_len: pos })
+}
+
+impl DecodeIndex {
+    pub fn stride(&self) -> usize { self.stride }
+
+    /// Length of the decompressed data.
+    pub fn decoded_len(&self) -> usize { self.decoded_len }
+
+    /// Like `decompress_at`, but starts decoding at the checkpoint before
+    /// `offset`. `comp` must be the stream the index was built from; any other
+    /// stream gives unspecified bytes or an error, but never a panic.
+    ///
+    /// Errors: as `decompress`, if `comp` is not the indexed stream.
+    pub fn seek(&self, comp: &[u8], offset: usize, len: usize) -> Result<Vec<u8>, Error> {
+        match self.points.get(offset / self.stride) {
+            Some(&(at, pos)) => window(DecodeTokens::resume(comp, at), pos, offset, len),
+            None => Ok(Vec::new()),
+        }
+    }
+}
+
+/// Decodes only the window `offset..offset + len` of the data `comp` decompresses to.
+///
+/// Tokens before the window are walked without expanding runs, and runs that
+/// straddle either edge are filled partially, so the cost is one pass over the
+/// compressed bytes up to the window plus `len`. The result is shorter than `len`
+/// if the data ends first (empty if it ends before `offset`).
+///
+/// Only the stream up to the end of the window is read, so errors past it go
+/// unnoticed; use `decompressed_len` to validate the whole stream.
+///
+/// Errors: as `decompress`, for the part of the stream that is read.
+pub fn decompress_at(comp: &[u8], offset: usize, len: usize) -> Result<Vec<u8>, Error> {
+    window(decode_tokens(comp), 0, offset, len)
+}
+
+/// Decodes the first `max_bytes` bytes of the data `comp` decompresses to (all of
+/// it, if shorter), for previews: decoding stops there, filling a straddling
+/// run partially, so neither the output nor its allocation exceeds `max_bytes`. Unlike
+/// `decompress_with_limit`, reaching the budget is not an error.
+///
+/// Errors: as `decompress_at(comp, 0, max_bytes)`.
+pub fn decompress_prefix(comp: &[u8], max_bytes: usize) -> Result<Vec<u8>, Error> {
+    decompress_at(comp, 0, max_bytes)
+}
+
+/// Collects `offset..offset + len` from `tokens`, whose first token starts at
+/// decoded position `pos`.
+pub(crate) fn window(tokens: DecodeTokens<'_>, mut pos: usize, offset: usize, len: usize) -> Result<Vec<u8>, Error> {
+    let end = offset.saturating_add(len);
+    // An upper bound, not the exact size: never more than `len` or than the
+    // remaining stream could decode (a 2-byte token yields at most 128 bytes).
+    let mut out = Vec::with_capacity(len.min(tokens.remaining().saturating_mul(64)));
+    for t in tokens {
+        if pos >= end { break; }
+        let (b, n) = match t? {
+            Token::Literal(b) => (b, 1),
+            Token::ZeroRun(n) => (0x00, n),
+            Token::FfRun(n) => (0xFF, n),
+        };
+        let (s, e) = (pos.max(offset), (pos + n).min(end));
+        if s < e {
+            out.resize(out.len() + (e - s), b);
+        }
+        pos += n;
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{compress, decompress};
+
+    #[test] fn windows_match_slices() {
+        let mut v = alloc::vec![0u8; 300];
+        v.extend_from_slice(&[0xFF; 70]);
+        v.extend_from_slice(&[0,0,0,0, 42, 0xFF,0xFF,0xFF, 1,2,3, 0, 0xFF, 0, 0xAA,0xBB, 0, 0xFF]);
+        let c = compress(&v);
+        for off in 0..=v.len() + 2 {
+            for len in [0, 1, 3, 127, 129, 400] {
+                let want = &v[off.min(v.len())..(off + len).min(v.len())];
+                assert_eq!(decompress_at(&c, off, len).unwrap(), want, "off {} len {}", off, len);
+            }
+        }
+        assert_eq!(decompress_at(&c, 0, usize::MAX).unwrap(), decompress(&c).unwrap());
+        assert_eq!(decompress_at(&c, usize::MAX, usize::MAX).unwrap(), []);
+    }
+    #[test] fn window_errors() {
+        let bad = [1 ^ 0xFF, 2 ^ 0xFF, 0xFF];
+        assert_eq!(decompress_at(&bad, 0, 2).unwrap(), [1, 2]); // error lies past the window
+        assert_eq!(decompress_at(&bad, 1, 5), Err(Error::RunMarkerWithoutControl));
+    }
+    #[test] fn prefix_truncates_gracefully() {
+        let mut v = alloc::vec![7u8; 10];
+        v.extend_from_slice(&[0; 1000]);
+        let c = compress(&v);
+        for n in [0, 1, 10, 11, 138, 139, 1010, 5000] {
+            let p = decompress_prefix(&c, n).unwrap();
+            assert_eq!(p, &v[..n.min(v.len())], "max_bytes {}", n);
+            assert!(p.capacity() <= n, "max_bytes {}", n);
+        }
+    }
+    #[test] fn index_seek_matches_every_stride() {
+        let mut v = alloc::vec![0u8; 300];
+        v.extend_from_slice(&[0xFF; 70]);
+        v.extend_from_slice(&[0,0,0,0, 42, 0xFF,0xFF,0xFF, 1,2,3, 0, 0xFF, 0, 0xAA,0xBB, 0, 0xFF]);
+        let c = compress(&v);
+        for stride in [1, 2, 3, 4, 5, 32, 127, 128, 129, 300, v.len() - 1, v.len(), v.len() + 1, usize::MAX] {
+            let idx = build_index(&c, stride).unwrap();
+            assert_eq!(idx.decoded_len(), v.len());
+            assert_eq!(idx.points.len(), (v.len() - 1) / stride + 1);
+            for off in 0..=v.len() + 1 {
+                for len in [0, 1, 7, 200] {
+                    assert_eq!(idx.seek(&c, off, len), decompress_at(&c, off, len), "stride {} off {}", stride, off);
+                }
+            }
+        }
+        let idx = build_index(&[], 8).unwrap();
+        assert_eq!((idx.decoded_len(), idx.seek(&[], 0, 4)), (0, Ok(alloc::vec![])));
+    }
+    #[test] fn index_checkpoints_inside_header() {
+        // Checkpoints in the negated 4-byte prefix resume with the right polarity.
+        let v = [5, 6, 0, 7, 8, 9];
+        let c = compress(&v);
+        let idx = build_index(&c, 1).unwrap();
+        assert_eq!(idx.points, [(0, 0), (1, 1), (2, 2), (4, 3), (5, 4), (6, 5)]);
+        for (off, &b) in v.iter().enumerate() {
+            assert_eq!(idx.seek(&c, off, 1).unwrap(), [b]);
+        }
+    }
+    #[test] fn index_rejects_bad_stream() {
+        assert_eq!(build_index(&[1 ^ 0xFF, 0xFF], 4), Err(Error::RunMarkerWithoutControl));
+        let idx = build_index(&compress(&[0; 1000]), 100).unwrap();
+        assert_eq!(idx.seek(&[1, 2], 500, 10), Ok(alloc::vec![])); // wrong stream: no panic
+    }
+    #[test]
+    #[should_panic(expected = "stride must be non-zero")]
+    fn index_zero_stride_panics() { let _ = build_index(&[], 0); }
+}
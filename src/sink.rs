@@ -0,0 +1,174 @@
+//! Output destinations for the encode and decode loops.
+
+/// Destination for encoded (or decoded) bytes; see [`compress_to_sink`](crate::compress_to_sink).
+///
+/// Provided for `Vec<u8>` (appends) and `&mut [u8]` (fills from the front and
+/// advances, like `io::Write for &mut [u8]`, but panics once full). Implement
+/// it to compress straight into other stores, e.g. a hashing writer.
+pub trait Sink {
+    fn push_byte(&mut self, b: u8);
+
+    /// Appends `s`.
+    #[inline]
+    fn push_slice(&mut self, s: &[u8]) {
+        for &b in s { self.push_byte(b); }
+    }
+
+    /// Appends `n` copies of `b` (a decoded run).
+    #[inline]
+    fn push_fill(&mut self, b: u8, n: usize) {
+        for _ in 0..n { self.push_byte(b); }
+    }
+}
+
+#[cfg(all(feature = "alloc", not(cdrle_allocator_api)))]
+impl Sink for alloc::vec::Vec<u8> {
+    #[inline]
+    fn push_byte(&mut self, b: u8) { self.push(b); }
+
+    #[inline]
+    fn push_slice(&mut self, s: &[u8]) { self.extend_from_slice(s); }
+
+    #[inline]
+    fn push_fill(&mut self, b: u8, n: usize) {
+        let base = self.len();
+        self.resize(base + n, b);
+    }
+}
+
+#[cfg(cdrle_allocator_api)]
+impl<A: core::alloc::Allocator> Sink for alloc::vec::Vec<u8, A> {
+    #[inline]
+    fn push_byte(&mut self, b: u8) { self.push(b); }
+
+    #[inline]
+    fn push_slice(&mut self, s: &[u8]) { self.extend_from_slice(s); }
+
+    #[inline]
+    fn push_fill(&mut self, b: u8, n: usize) {
+        let base = self.len();
+        self.resize(base + n, b);
+    }
+}
+
+impl Sink for &mut [u8] {
+    #[inline]
+    fn push_byte(&mut self, b: u8) {
+        let (first, rest) = core::mem::take(self).split_first_mut().expect("Sink: slice is full");
+        *first = b;
+        *self = rest;
+    }
+
+    #[inline]
+    fn push_slice(&mut self, s: &[u8]) {
+        let (head, rest) = core::mem::take(self).split_at_mut(s.len());
+        head.copy_from_slice(s);
+        *self = rest;
+    }
+
+    #[inline]
+    fn push_fill(&mut self, b: u8, n: usize) {
+        let (head, rest) = core::mem::take(self).split_at_mut(n);
+        head.fill(b);
+        *self = rest;
+    }
+}
+
+/// XOR-negates the first `left` bytes on their way into `inner`.
+pub(crate) struct Negate<'a, S: Sink + ?Sized> {
+    pub(crate) inner: &'a mut S,
+    pub(crate) left: usize,
+}
+
+impl<S: Sink + ?Sized> Sink for Negate<'_, S> {
+    #[inline]
+    fn push_byte(&mut self, b: u8) {
+        if self.left != 0 {
+            self.left -= 1;
+            self.inner.push_byte(b ^ 0xFF);
+        } else {
+            self.inner.push_byte(b);
+        }
+    }
+
+    #[inline]
+    fn push_slice(&mut self, s: &[u8]) {
+        let k = self.left.min(s.len());
+        for &b in &s[..k] { self.push_byte(b); }
+        self.inner.push_slice(&s[k..]);
+    }
+}
+
+/// Writes into a fixed buffer. Bytes past the end are counted but dropped,
+/// so `len` ends up as the size the output needed.
+pub(crate) struct SliceSink<'a> {
+    pub(crate) buf: &'a mut [u8],
+    pub(crate) len: usize,
+}
+
+impl Sink for SliceSink<'_> {
+    #[inline]
+    fn push_byte(&mut self, b: u8) {
+        if let Some(slot) = self.buf.get_mut(self.len) { *slot = b; }
+        self.len += 1;
+    }
+
+    #[inline]
+    fn push_fill(&mut self, b: u8, n: usize) {
+        let end = self.len + n;
+        if end <= self.buf.len() { self.buf[self.len..end].fill(b); }
+        self.len = end;
+    }
+}
+
+/// Discards output, keeping only its length.
+#[derive(Default)]
+pub(crate) struct Counter(pub(crate) usize);
+
+impl Sink for Counter {
+    #[inline]
+    fn push_byte(&mut self, _: u8) { self.0 += 1; }
+
+    #[inline]
+    fn push_fill(&mut self, _: u8, n: usize) { self.0 += n; }
+}
+
+/// Writes encoder output over its own already-consumed input. Output that
+/// would overtake unread input waits in `spill`, a queue that drains back into
+/// `buf` as soon as more input is consumed; so `spill` never holds more than the
+/// current overtake (compressed prefix length minus consumed input length).
+#[cfg(feature = "alloc")]
+pub(crate) struct InPlace<'a> {
+    pub(crate) buf: &'a mut [u8],
+    pub(crate) w: usize, // next write index in `buf`
+    pub(crate) r: usize, // input bytes consumed; `buf[..r]` may be overwritten
+    pub(crate) spill: alloc::collections::VecDeque<u8>,
+}
+
+#[cfg(feature = "alloc")]
+impl InPlace<'_> {
+    /// Marks `buf[..r]` as consumed and moves queued output into the freed room.
+    #[inline]
+    pub(crate) fn consume(&mut self, r: usize) {
+        self.r = r;
+        while self.w < self.r {
+            match self.spill.pop_front() {
+                Some(b) => { self.buf[self.w] = b; self.w += 1; }
+                None => break,
+            }
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl Sink for InPlace<'_> {
+    #[inline]
+    fn push_byte(&mut self, b: u8) {
+        if self.w < self.r && self.spill.is_empty() {
+            self.buf[self.w] = b;
+            self.w += 1;
+        } else {
+            self.spill.push_back(b);
+        }
+    }
+}
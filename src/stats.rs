@@ -0,0 +1,125 @@
+//! Compression statistics.
+
+use alloc::vec::Vec;
+
+use crate::encode::{negate_header, Encoder};
+use crate::iter::{runs, Run};
+use crate::{compressed_len, MAX_FF_RUN, MAX_ZERO_RUN};
+
+/// What `compress` did with an input; see [`compress_with_stats`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CompressStats {
+    pub input_len: usize,
+    pub output_len: usize,
+    /// Input bytes copied through as literals.
+    pub literal_bytes: usize,
+    /// Encoded 0x00 runs (a run longer than `MAX_ZERO_RUN` counts once per piece).
+    pub zero_run_count: usize,
+    /// Encoded 0xFF runs (a run longer than `MAX_FF_RUN` counts once per piece).
+    pub ff_run_count: usize,
+}
+
+impl CompressStats {
+    /// `output_len / input_len`, as [`compression_ratio`] (1.0 for empty input).
+    pub fn ratio(&self) -> f64 { ratio(self.output_len, self.input_len) }
+}
+
+/// `compressed_len(input) / input.len()`: below 1.0 the input shrinks, above it
+/// expands (at most 2.0). Computed without building the output. An empty input
+/// compresses to an empty stream, which counts as unchanged: the ratio is 1.0.
+pub fn compression_ratio(input: &[u8]) -> f64 {
+    ratio(compressed_len(input), input.len())
+}
+
+fn ratio(out: usize, input: usize) -> f64 {
+    if input == 0 { 1.0 } else { out as f64 / input as f64 }
+}
+
+/// Distribution of the runs `compress` encodes; see [`run_histogram`].
+///
+/// `zero[k]` counts 0x00 runs of length `k + 1`, `ff[k]` 0xFF runs of length
+/// `k + 1`. Runs are counted as encoded, split at `MAX_ZERO_RUN`/`MAX_FF_RUN`,
+/// so a large count in the last bucket means long runs that a varint length
+/// (see `compress_v2`) would encode in fewer tokens.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RunHistogram {
+    pub zero: [usize; MAX_ZERO_RUN],
+    pub ff: [usize; MAX_FF_RUN],
+}
+
+/// Tallies the run lengths of `input` in one pass over [`runs`].
+pub fn run_histogram(input: &[u8]) -> RunHistogram {
+    let mut h = RunHistogram { zero: [0; MAX_ZERO_RUN], ff: [0; MAX_FF_RUN] };
+    for r in runs(input) {
+        match r {
+            Run::Zeros(n) => h.zero[n - 1] += 1,
+            Run::Ffs(n) => h.ff[n - 1] += 1,
+            Run::Literal(_) => {}
+        }
+    }
+    h
+}
+
+/// Like [`compress`](crate::compress), also returning statistics gathered by
+/// the same encoding pass.
+pub fn compress_with_stats(input: &[u8]) -> (Vec<u8>, CompressStats) {
+    let mut out = Vec::with_capacity(input.len());
+    let mut enc = Encoder::default();
+    enc.push(input, &mut out);
+    enc.flush(&mut out);
+    negate_header(&mut out);
+    let [zero_run_count, ff_run_count] = enc.runs();
+    let stats = CompressStats {
+        input_len: input.len(),
+        output_len: out.len(),
+        literal_bytes: out.len() - 2 * (zero_run_count + ff_run_count),
+        zero_run_count,
+        ff_run_count,
+    };
+    (out, stats)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compress;
+
+    #[test] fn stats_mixed() {
+        let v = [0,0,0,0, 42, 0xFF,0xFF,0xFF, 1,2,3, 0, 0xFF, 0, 0xAA,0xBB, 0, 0xFF];
+        let (c, s) = compress_with_stats(&v);
+        assert_eq!(c, compress(&v));
+        assert_eq!(s, CompressStats {
+            input_len: 18,
+            output_len: 20,
+            literal_bytes: 6,
+            zero_run_count: 4,
+            ff_run_count: 3,
+        });
+        assert_eq!(s.output_len, s.literal_bytes + 2 * (s.zero_run_count + s.ff_run_count));
+    }
+    #[test] fn ratios() {
+        assert_eq!(compression_ratio(&[]), 1.0);
+        assert_eq!(compression_ratio(&[1, 2, 3, 4]), 1.0);
+        assert_eq!(compression_ratio(&[0; 256]), 4.0 / 256.0);
+        assert_eq!(compression_ratio(&[0, 0xFF]), 2.0);
+        let v = [0,0,0,0, 42, 0xFF,0xFF,0xFF, 1,2,3, 0, 0xFF, 0, 0xAA,0xBB, 0, 0xFF];
+        assert_eq!(compress_with_stats(&v).1.ratio(), compression_ratio(&v));
+        assert_eq!(CompressStats::default().ratio(), 1.0);
+    }
+    #[test] fn histogram_mixed() {
+        let v = [0,0,0,0, 42, 0xFF,0xFF,0xFF, 1,2,3, 0, 0xFF, 0, 0xAA,0xBB, 0, 0xFF];
+        let h = run_histogram(&v);
+        assert_eq!((h.zero[0], h.zero[3], h.ff[0], h.ff[2]), (3, 1, 2, 1));
+        let s = compress_with_stats(&v).1;
+        assert_eq!((h.zero.iter().sum::<usize>(), h.ff.iter().sum::<usize>()), (s.zero_run_count, s.ff_run_count));
+        let h = run_histogram(&[0; 300]);
+        assert_eq!((h.zero[127], h.zero[43], h.zero.iter().sum::<usize>()), (2, 1, 3));
+        assert_eq!(run_histogram(&[]), RunHistogram { zero: [0; 128], ff: [0; 32] });
+    }
+    #[test] fn stats_split_runs() {
+        let (_, s) = compress_with_stats(&[0; 300]);
+        assert_eq!((s.zero_run_count, s.ff_run_count, s.literal_bytes), (3, 0, 0));
+        let (_, s) = compress_with_stats(&[0xFF; 65]);
+        assert_eq!((s.zero_run_count, s.ff_run_count, s.output_len), (0, 3, 6));
+    }
+}
@@ -0,0 +1,255 @@
+//! Incremental compression and decompression for data that arrives in chunks.
+
+use alloc::vec::Vec;
+
+use crate::decode::Decoder;
+use crate::encode::{negate_header, Encoder, BLOCK_END};
+use crate::Error;
+
+/// Incremental compressor.
+///
+/// Feed input in arbitrary chunks with [`push`](Compressor::push), then call
+/// [`finish`](Compressor::finish). Pending 0x00/0xFF runs are carried across
+/// `push` boundaries, so the result is byte-identical to [`compress`](crate::compress)
+/// on the concatenated input.
+#[derive(Clone, Debug, Default)]
+pub struct Compressor {
+    out: Vec<u8>,
+    enc: Encoder,
+    block: usize, // start of the current block in `out`
+}
+
+impl Compressor {
+    pub fn new() -> Self { Self::default() }
+
+    /// Like `new`, but preallocates `cap` bytes of output.
+    pub fn with_capacity(cap: usize) -> Self {
+        Self { out: Vec::with_capacity(cap), enc: Encoder::default(), block: 0 }
+    }
+
+    /// Run-encodes `chunk`. Runs still open at the end of `chunk` stay pending.
+    pub fn push(&mut self, chunk: &[u8]) {
+        self.enc.push(chunk, &mut self.out);
+    }
+
+    /// Starts a fresh stream, keeping the output allocation. Discards pending
+    /// runs and any output not yet taken with `finish` or `finish_into`.
+    pub fn reset(&mut self) {
+        self.out.clear();
+        self.enc = Encoder::default();
+        self.block = 0;
+    }
+
+    /// Ends the current block: flushes pending runs, appends a block terminator,
+    /// and negates the block's first 4 bytes, so the block decodes on its own
+    /// with [`decompress_block`](crate::decompress_block). Input pushed afterwards
+    /// starts a new, independent block; the bytes after the last block (what
+    /// `finish` adds) form a final unterminated one.
+    ///
+    /// A terminated block is *not* a `decompress` stream: its terminator reads as an
+    /// invalid run.
+    pub fn flush_block(&mut self) {
+        self.enc.flush(&mut self.out);
+        self.out.extend_from_slice(&BLOCK_END);
+        negate_header(&mut self.out[self.block..]);
+        self.block = self.out.len();
+    }
+
+    /// Flushes pending runs and XOR-negates the first 4 bytes of the compressed stream.
+    pub fn finish(mut self) -> Vec<u8> {
+        let mut out = Vec::new();
+        self.finish_into(&mut out);
+        out
+    }
+
+    /// Like `finish`, but keeps the compressor for the next stream: the
+    /// compressed stream replaces the contents of `out`, and the compressor is
+    /// reset, taking over `out`'s old allocation for its next output. Passing
+    /// the same `out` for every message cycles two allocations between them.
+    pub fn finish_into(&mut self, out: &mut Vec<u8>) {
+        self.enc.flush(&mut self.out);
+        // Negate first 4 bytes of *compressed* stream (once, however many pushes).
+        negate_header(&mut self.out[self.block..]);
+        core::mem::swap(&mut self.out, out);
+        self.reset();
+    }
+}
+
+/// Incremental decompressor.
+///
+/// Feed a compressed stream in arbitrary chunks with [`push`](Decompressor::push),
+/// then call [`finish`](Decompressor::finish). A run marker at the end of one chunk
+/// may have its CONTROL byte at the start of the next; the 4-byte header negation
+/// is tracked by absolute stream position, not per chunk.
+#[derive(Clone, Debug, Default)]
+pub struct Decompressor {
+    out: Vec<u8>,
+    dec: Decoder,
+}
+
+impl Decompressor {
+    pub fn new() -> Self { Self::default() }
+
+    /// Like `new`, but preallocates `cap` bytes of output.
+    pub fn with_capacity(cap: usize) -> Self {
+        Self { out: Vec::with_capacity(cap), dec: Decoder::default() }
+    }
+
+    /// Decompresses into `out` after clearing it, keeping its allocation.
+    pub(crate) fn with_buffer(mut out: Vec<u8>) -> Self {
+        out.clear();
+        Self { out, dec: Decoder::default() }
+    }
+
+    /// Decodes `chunk`. A trailing run marker stays pending until the next `push`.
+    ///
+    /// Errors:
+    /// - InvalidRunLength{len}    (FF-run with len > 32)
+    /// - OutputTooLarge{max}      (total decoded size would pass `DEFAULT_MAX_OUTPUT`)
+    ///
+    /// After an error the stream is corrupt and the decompressor should be discarded.
+    pub fn push(&mut self, chunk: &[u8]) -> Result<(), Error> {
+        self.dec.push(chunk, &mut self.out)
+    }
+
+    /// Starts a fresh stream, keeping the output allocation. Discards any
+    /// dangling run marker, the header-negation position, and output not yet
+    /// taken with `finish` or `finish_into`; also recovers a decompressor after
+    /// an error.
+    pub fn reset(&mut self) {
+        self.out.clear();
+        self.dec = Decoder::default();
+    }
+
+    /// Returns the decompressed output.
+    ///
+    /// Errors:
+    /// - RunMarkerWithoutControl  (stream ended with a dangling 0x00)
+    pub fn finish(mut self) -> Result<Vec<u8>, Error> {
+        let mut out = Vec::new();
+        self.finish_into(&mut out).map(|()| out)
+    }
+
+    /// Like `finish`, but keeps the decompressor for the next stream: the
+    /// output replaces the contents of `out` (even on error, so callers keep the
+    /// partial result), and the decompressor is reset, taking over `out`'s old
+    /// allocation.
+    ///
+    /// Errors:
+    /// - RunMarkerWithoutControl  (stream ended with a dangling 0x00)
+    pub fn finish_into(&mut self, out: &mut Vec<u8>) -> Result<(), Error> {
+        core::mem::swap(&mut self.out, out);
+        let r = self.dec.finish();
+        self.reset();
+        r
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{compress, decompress};
+    use alloc::vec;
+
+    fn chunked(v: &[u8], n: usize) -> Vec<u8> {
+        let mut c = Compressor::new();
+        for chunk in v.chunks(n) { c.push(chunk); }
+        c.finish()
+    }
+
+    #[test] fn matches_compress() {
+        let mut v = vec![0u8; 300];
+        v.extend_from_slice(&[0xFF; 70]);
+        v.extend_from_slice(&[0,0,0,0, 42, 0xFF,0xFF,0xFF, 1,2,3, 0, 0xFF, 0, 0xAA,0xBB, 0, 0xFF]);
+        for n in [1, 2, 3, 4, 5, 31, 32, 33, 127, 128, 129, v.len()] {
+            let c = chunked(&v, n);
+            assert_eq!(c, compress(&v), "chunk size {}", n);
+            assert_eq!(decompress(&c).unwrap(), v);
+        }
+    }
+    #[test] fn empty() {
+        assert_eq!(Compressor::new().finish(), compress(&[]));
+        let mut c = Compressor::new();
+        c.push(&[]);
+        c.push(&[0]);
+        c.push(&[]);
+        assert_eq!(c.finish(), compress(&[0]));
+    }
+
+    fn dechunked(c: &[u8], n: usize) -> Result<Vec<u8>, Error> {
+        let mut d = Decompressor::new();
+        for chunk in c.chunks(n) { d.push(chunk)?; }
+        d.finish()
+    }
+
+    #[test] fn decompressor_matches_decompress() {
+        let v = [0,0,0,0, 42, 0xFF,0xFF,0xFF, 1,2,3, 0, 0xFF, 0, 0xAA,0xBB, 0, 0xFF];
+        let c = compress(&v);
+        for n in 1..=c.len() {
+            assert_eq!(dechunked(&c, n).unwrap(), v, "chunk size {}", n);
+        }
+    }
+    #[test] fn marker_split_across_chunks() {
+        // Marker is the last byte of the first chunk, both inside and past the negated header.
+        for prefix in [&[][..], &[1, 2, 3, 4, 5][..]] {
+            let mut v = prefix.to_vec();
+            v.extend_from_slice(&[0; 10]);
+            let c = compress(&v);
+            let (a, b) = c.split_at(prefix.len() + 1);
+            let mut d = Decompressor::new();
+            d.push(a).unwrap();
+            d.push(b).unwrap();
+            assert_eq!(d.finish().unwrap(), v);
+        }
+    }
+    #[test] fn dangling_marker_at_finish() {
+        let mut d = Decompressor::new();
+        d.push(&[0xFF]).unwrap(); // negated 0x00
+        assert_eq!(d.finish(), Err(Error::RunMarkerWithoutControl));
+    }
+    #[test] fn invalid_run_in_second_chunk() {
+        let mut d = Decompressor::new();
+        d.push(&[0xFF]).unwrap();
+        assert_eq!(d.push(&[0xA0 ^ 0xFF]), Err(Error::InvalidRunLength { len: 33 }));
+    }
+    #[test] fn reset_starts_fresh_stream() {
+        let mut c = Compressor::with_capacity(64);
+        c.push(&[0, 0, 1, 2, 3, 4, 0xFF]);
+        c.reset();
+        c.push(&[5, 0]);
+        assert_eq!(c.finish(), compress(&[5, 0]));
+
+        let mut d = Decompressor::with_capacity(64);
+        d.push(&compress(&[1, 2, 3, 4, 5])).unwrap();
+        d.push(&[0x00]).unwrap(); // dangling marker
+        d.reset();
+        let cap = d.out.capacity();
+        d.push(&compress(&[9, 0, 0])).unwrap();
+        assert_eq!(d.out.capacity(), cap);
+        assert_eq!(d.finish().unwrap(), [9, 0, 0]);
+
+        let mut d = Decompressor::new();
+        assert_eq!(d.push(&[1 ^ 0xFF, 0xFF, 0xFF ^ 0xFF]), Err(Error::InvalidRunLength { len: 128 }));
+        d.reset();
+        d.push(&compress(&[0xFF])).unwrap();
+        assert_eq!(d.finish().unwrap(), [0xFF]);
+    }
+    #[test] fn finish_into_reuses_instance_across_messages() {
+        let msgs = [&[1u8, 0, 0, 0xFF, 2][..], &[], &[0; 300], &[7, 8, 9, 10, 11, 12]];
+        let (mut c, mut d) = (Compressor::new(), Decompressor::new());
+        let (mut comp, mut out) = (Vec::new(), Vec::new());
+        for m in msgs {
+            for chunk in m.chunks(3) { c.push(chunk); }
+            c.finish_into(&mut comp);
+            assert_eq!(comp, compress(m));
+            for chunk in comp.chunks(2) { d.push(chunk).unwrap(); }
+            d.finish_into(&mut out).unwrap();
+            assert_eq!(out, m);
+        }
+        d.push(&[0xFF]).unwrap(); // dangling marker
+        assert_eq!(d.finish_into(&mut out), Err(Error::RunMarkerWithoutControl));
+        assert_eq!(out, []);
+        d.push(&compress(&[3, 0])).unwrap();
+        assert_eq!(d.finish().unwrap(), [3, 0]);
+    }
+}
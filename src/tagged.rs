@@ -0,0 +1,160 @@
+//! Streams prefixed with a mode tag or format header.
+
+use alloc::vec::Vec;
+
+use crate::encode::{negate_header, Encoder};
+use crate::{compress, compress_v2, compressed_len, decompress, decompress_v2, Error};
+
+const TAG_RAW: u8 = 0x00;
+const TAG_RLE: u8 = 0x01;
+
+/// First byte of a `compress_tagged` header. An untagged v1 stream starting with
+/// `0xFF` must follow it with a (negated) control byte in `0x60..=0xFF`, so
+/// `[MAGIC, version]` with `version < 0x60` can never begin valid legacy data.
+const MAGIC: u8 = 0xFF;
+
+/// Wire format selected by `compress_tagged`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    /// The default format (`compress`): runs capped at 128 zeros / 32 0xFF bytes.
+    V1,
+    /// Varint run lengths (`compress_v2`).
+    V2,
+}
+
+impl Format {
+    const fn version(self) -> u8 {
+        match self {
+            Format::V1 => 0x01,
+            Format::V2 => 0x02,
+        }
+    }
+}
+
+/// Compresses `input` unless that would not make it smaller, and tags the result:
+/// `0x01` + `compress(input)`, or `0x00` + `input` stored raw.
+///
+/// The output is never longer than `input.len() + 1`. Decode with `decompress_best`.
+pub fn compress_best(input: &[u8]) -> Vec<u8> {
+    let n = compressed_len(input);
+    if n >= input.len() {
+        let mut out = Vec::with_capacity(input.len() + 1);
+        out.push(TAG_RAW);
+        out.extend_from_slice(input);
+        return out;
+    }
+    let mut out = Vec::with_capacity(n + 1);
+    out.push(TAG_RLE);
+    let mut enc = Encoder::default();
+    enc.push(input, &mut out);
+    enc.flush(&mut out);
+    negate_header(&mut out[1..]);
+    out
+}
+
+/// Decodes the output of `compress_best`.
+///
+/// Errors: as `decompress`, plus
+/// - UnknownFormat  (empty input, or an unknown mode tag)
+pub fn decompress_best(comp: &[u8]) -> Result<Vec<u8>, Error> {
+    match comp.split_first() {
+        Some((&TAG_RAW, raw)) => Ok(raw.to_vec()),
+        Some((&TAG_RLE, rle)) => decompress(rle),
+        _ => Err(Error::UnknownFormat),
+    }
+}
+
+/// Compresses `input` in `format` behind a 2-byte header `[0xFF, version]`.
+/// Decode with `decompress_auto`.
+pub fn compress_tagged(input: &[u8], format: Format) -> Vec<u8> {
+    let body = match format {
+        Format::V1 => compress(input),
+        Format::V2 => compress_v2(input),
+    };
+    let mut out = Vec::with_capacity(body.len() + 2);
+    out.push(MAGIC);
+    out.push(format.version());
+    out.extend_from_slice(&body);
+    out
+}
+
+/// Decodes the output of `compress_tagged`, dispatching on its header.
+///
+/// No valid untagged v1 stream starts with a `compress_tagged` header, so already
+/// persisted v1 data needs no rewrite: fall back to `decompress` on
+/// `UnknownFormat`, and re-encode with `compress_tagged` at leisure.
+///
+/// ```
+/// # use cdrle::{compress, compress_tagged, decompress, decompress_auto, Error, Format};
+/// let load = |c: &[u8]| match decompress_auto(c) {
+///     Err(Error::UnknownFormat) => decompress(c),
+///     r => r,
+/// };
+/// assert_eq!(load(&compress(b"legacy")).unwrap(), b"legacy");
+/// assert_eq!(load(&compress_tagged(b"new", Format::V2)).unwrap(), b"new");
+/// ```
+///
+/// Errors: as `decompress` / `decompress_v2`, plus
+/// - UnknownFormat  (missing header, e.g. untagged data, or an unknown version)
+pub fn decompress_auto(comp: &[u8]) -> Result<Vec<u8>, Error> {
+    match comp {
+        [MAGIC, v, body @ ..] if *v == Format::V1.version() => decompress(body),
+        [MAGIC, v, body @ ..] if *v == Format::V2.version() => decompress_v2(body),
+        _ => Err(Error::UnknownFormat),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compress;
+
+    #[test] fn best_picks_smaller() {
+        let sparse = [0u8; 100];
+        let c = compress_best(&sparse);
+        assert_eq!(c[0], TAG_RLE);
+        assert_eq!(&c[1..], &compress(&sparse)[..]);
+        assert_eq!(decompress_best(&c).unwrap(), sparse);
+
+        let alt: Vec<u8> = (0..100).map(|i| if i % 2 == 0 { 0x00 } else { 0xFF }).collect();
+        let c = compress_best(&alt);
+        assert_eq!(c.len(), alt.len() + 1);
+        assert_eq!(c[0], TAG_RAW);
+        assert_eq!(decompress_best(&c).unwrap(), alt);
+
+        for v in [&[][..], &[1, 2, 3], &[0]] {
+            let c = compress_best(v);
+            assert!(c.len() <= v.len() + 1);
+            assert_eq!(decompress_best(&c).unwrap(), v);
+        }
+    }
+    #[test] fn best_rejects_bad_tag() {
+        assert_eq!(decompress_best(&[]), Err(Error::UnknownFormat));
+        assert_eq!(decompress_best(&[0x7E, 1]), Err(Error::UnknownFormat));
+        assert_eq!(decompress_best(&[TAG_RLE, 0xFF]), Err(Error::RunMarkerWithoutControl));
+    }
+    #[test] fn auto_dispatches() {
+        let input = [0,0,0,0, 42, 0xFF,0xFF,0xFF, 1,2,3, 0, 0xFF, 0, 0xAA,0xBB, 0, 0xFF];
+        for f in [Format::V1, Format::V2] {
+            let c = compress_tagged(&input, f);
+            assert_eq!(&c[..2], &[MAGIC, f.version()]);
+            assert_eq!(decompress_auto(&c).unwrap(), input);
+        }
+        assert_eq!(&compress_tagged(&input, Format::V2)[2..], &compress_v2(&input)[..]);
+        assert_eq!(decompress_auto(&compress_tagged(&[], Format::V1)).unwrap(), b"");
+    }
+    #[test] fn auto_rejects_untagged() {
+        assert_eq!(decompress_auto(&[]), Err(Error::UnknownFormat));
+        assert_eq!(decompress_auto(&[MAGIC]), Err(Error::UnknownFormat));
+        assert_eq!(decompress_auto(&[MAGIC, 0x03]), Err(Error::UnknownFormat));
+        // Every v1 stream, whatever its first two bytes, is untagged.
+        for a in 0..=255u8 {
+            for b in 0..=255u8 {
+                let c = [a, b];
+                if decompress(&c).is_ok() {
+                    assert_eq!(decompress_auto(&c), Err(Error::UnknownFormat), "{:x?}", c);
+                }
+            }
+        }
+    }
+}
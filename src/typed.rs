@@ -0,0 +1,70 @@
+//! A compressed stream known to be well-formed.
+
+use alloc::vec::Vec;
+use core::ops::Deref;
+
+use crate::decode::Decoder;
+use crate::{compress, validate, Error};
+
+/// A compressed stream that has passed [`validate`], so decoding it cannot fail.
+///
+/// Build one with [`compress_typed`] or, for bytes of unknown origin,
+/// [`Compressed::from_compressed`] / `TryFrom<Vec<u8>>`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Compressed(Vec<u8>);
+
+/// Like `compress`, but returns the stream as a [`Compressed`].
+pub fn compress_typed(input: &[u8]) -> Compressed { Compressed(compress(input)) }
+
+impl Compressed {
+    /// Wraps `comp` after checking it with `validate`.
+    ///
+    /// Errors: as `validate`.
+    pub fn from_compressed(comp: Vec<u8>) -> Result<Self, Error> {
+        validate(&comp)?;
+        Ok(Self(comp))
+    }
+
+    /// Decompresses the stream. The output is not capped by `DEFAULT_MAX_OUTPUT`:
+    /// a valid stream decodes in full, up to 64x its size.
+    pub fn decompress(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(self.0.len());
+        let mut dec = Decoder::with_limit(usize::MAX);
+        dec.push(&self.0, &mut out).and_then(|()| dec.finish()).expect("Compressed holds a validated stream");
+        out
+    }
+
+    pub fn into_vec(self) -> Vec<u8> { self.0 }
+}
+
+impl TryFrom<Vec<u8>> for Compressed {
+    type Error = Error;
+    fn try_from(comp: Vec<u8>) -> Result<Self, Error> { Self::from_compressed(comp) }
+}
+
+impl Deref for Compressed {
+    type Target = [u8];
+    fn deref(&self) -> &[u8] { &self.0 }
+}
+
+impl AsRef<[u8]> for Compressed {
+    fn as_ref(&self) -> &[u8] { &self.0 }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test] fn typed_roundtrip_and_validation() {
+        let v = [0,0,0,0, 42, 0xFF,0xFF,0xFF, 1,2,3, 0, 0xFF, 0, 0xAA,0xBB, 0, 0xFF];
+        let c = compress_typed(&v);
+        assert_eq!(&*c, &compress(&v)[..]);
+        assert_eq!(c.decompress(), v);
+        let c2 = Compressed::try_from(c.clone().into_vec()).unwrap();
+        assert_eq!(c2, c);
+        assert_eq!(c2.as_ref().len(), c.len());
+        assert_eq!(Compressed::from_compressed(alloc::vec![1, 0xFF]), Err(Error::RunMarkerWithoutControl));
+        assert_eq!(Compressed::try_from(alloc::vec![0xFF, 0xA0 ^ 0xFF]), Err(Error::InvalidRunLength { len: 33 }));
+        assert!(compress_typed(&[]).decompress().is_empty());
+    }
+}
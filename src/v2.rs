@@ -0,0 +1,179 @@
+//! Version-2 format: uncapped runs with varint lengths.
+//!
+//! On the wire, v2 differs from v1 only in what follows a run marker:
+//!
+//! - Literals: any byte != `0x00` (as in v1)
+//! - Runs: `(0x00, VARINT)` where `VARINT` is unsigned LEB128 of
+//!   `(len - 1) << 1 | is_ff` (`bit0=0→zeros`, `bit0=1→0xFF`), so a run of any
+//!   length costs one marker plus 1..=10 length bytes, instead of one 2-byte
+//!   pair per 128 zeros / 32 0xFF bytes.
+//! - The first 4 bytes of the compressed stream are XOR-negated (as in v1).
+//!
+//! Runs of up to 64 bytes encode as one length byte, so short-run data compresses
+//! exactly as in v1 (runs of 33..=64 0xFF bytes even save a pair). The formats
+//! are not distinguishable from their bytes; see `compress_tagged` for a
+//! self-describing container.
+//!
+//! The opt-in *sparse* variant (`compress_v2_sparse`) also encodes a lone 0x00
+//! as the single byte `0xFF`. No v2 stream has a literal 0xFF (0xFF input bytes
+//! are always runs), so the escape is unambiguous, and a zero between literals
+//! costs 1 byte instead of 2.
+
+use alloc::vec::Vec;
+
+use crate::encode::negate_header;
+use crate::frame::put_varint;
+use crate::scan::run_len;
+use crate::{Error, DEFAULT_MAX_OUTPUT};
+
+/// In the sparse variant, stands for a lone 0x00 where a literal is expected.
+const LONE_ZERO: u8 = 0xFF;
+
+/// Compresses `input` to the v2 format. Decode with `decompress_v2`.
+pub fn compress_v2(input: &[u8]) -> Vec<u8> { encode(input, false) }
+
+/// Compresses `input` to the sparse v2 variant: v2, but each lone 0x00 costs one
+/// byte. Decode with `decompress_v2_sparse`; the output never grows past v2's.
+pub fn compress_v2_sparse(input: &[u8]) -> Vec<u8> { encode(input, true) }
+
+/// Decompresses `comp` produced by `compress_v2`.
+///
+/// Errors:
+/// - TruncatedVarint              (a run length is cut off by the end of the stream, or overflows `usize`)
+/// - OutputTooLarge{max}          (decoded size would pass `DEFAULT_MAX_OUTPUT`)
+pub fn decompress_v2(comp: &[u8]) -> Result<Vec<u8>, Error> { decode(comp, false) }
+
+/// Decompresses `comp` produced by `compress_v2_sparse`.
+///
+/// Errors: as `decompress_v2`.
+pub fn decompress_v2_sparse(comp: &[u8]) -> Result<Vec<u8>, Error> { decode(comp, true) }
+
+fn encode(input: &[u8], sparse: bool) -> Vec<u8> {
+    let mut out = Vec::with_capacity(input.len());
+    let mut i = 0;
+    while i < input.len() {
+        let b = input[i];
+        if b != 0x00 && b != 0xFF {
+            out.push(b);
+            i += 1;
+            continue;
+        }
+        let n = run_len(&input[i..], b);
+        i += n;
+        if sparse && n == 1 && b == 0x00 {
+            out.push(LONE_ZERO);
+            continue;
+        }
+        out.push(0x00);
+        put_varint(&mut out, (n - 1) << 1 | (b == 0xFF) as usize);
+    }
+    negate_header(&mut out);
+    out
+}
+
+fn decode(comp: &[u8], sparse: bool) -> Result<Vec<u8>, Error> {
+    let max = DEFAULT_MAX_OUTPUT;
+    let mut out = Vec::with_capacity(comp.len());
+    let mut i = 0usize;
+
+    #[inline]
+    fn read_unneg(comp: &[u8], i: &mut usize) -> Option<u8> {
+        let mut b = *comp.get(*i)?;
+        if *i < 4 { b ^= 0xFF; }
+        *i += 1;
+        Some(b)
+    }
+
+    while let Some(b) = read_unneg(comp, &mut i) {
+        if b != 0x00 {
+            out.push(if sparse && b == LONE_ZERO { 0x00 } else { b });
+            continue;
+        }
+        // Not `frame::get_varint`: these bytes may fall in the negated header.
+        let mut v = 0usize;
+        let mut shift = 0u32;
+        loop {
+            let c = read_unneg(comp, &mut i).ok_or(Error::TruncatedVarint)?;
+            let low = (c & 0x7F) as usize;
+            if shift >= usize::BITS || low << shift >> shift != low {
+                return Err(Error::TruncatedVarint);
+            }
+            v |= low << shift;
+            shift += 7;
+            if c & 0x80 == 0 { break; }
+        }
+        let len = (v >> 1) + 1;
+        if len > max - out.len() {
+            return Err(Error::OutputTooLarge { max });
+        }
+        let fill = if v & 1 != 0 { 0xFF } else { 0x00 };
+        let base = out.len();
+        out.resize(base + len, fill);
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compress;
+    use alloc::vec;
+
+    fn rt(v: &[u8]) {
+        let c = compress_v2(v);
+        assert_eq!(decompress_v2(&c).unwrap(), v, "in:{:x?} cmp:{:x?}", v, c);
+    }
+
+    #[test] fn v2_roundtrip() {
+        rt(&[]);
+        rt(&[1, 2, 3]);
+        rt(&[0; 1]);
+        rt(&[0xFF; 1]);
+        rt(&[0,0,0,0, 42, 0xFF,0xFF,0xFF, 1,2,3, 0, 0xFF, 0, 0xAA,0xBB, 0, 0xFF]);
+        rt(&vec![0; 1 << 20]);
+        rt(&vec![0xFF; 100_000]);
+    }
+    #[test] fn v2_long_runs_are_short() {
+        assert_eq!(compress_v2(&vec![0; 1 << 20]).len(), 4); // marker + 3 length bytes
+        assert!(compress(&vec![0; 1 << 20]).len() > 16_000);
+        let mixed = [0,0,0,0, 42, 0xFF,0xFF,0xFF, 1,2,3, 0, 0xFF, 0, 0xAA,0xBB, 0, 0xFF];
+        assert_eq!(compress_v2(&mixed).len(), compress(&mixed).len());
+    }
+    #[test] fn v2_sparse_roundtrip() {
+        let mixed = [0,0,0,0, 42, 0xFF,0xFF,0xFF, 1,2,3, 0, 0xFF, 0, 0xAA,0xBB, 0, 0xFF];
+        for v in [&mixed[..], &[], &[0], &[0xFF], &[0, 0], &[1, 0, 2], &vec![0; 1000]] {
+            let c = compress_v2_sparse(v);
+            assert_eq!(decompress_v2_sparse(&c).unwrap(), v, "in:{:x?} cmp:{:x?}", v, c);
+            assert!(c.len() <= compress_v2(v).len());
+        }
+        assert_eq!(compress_v2_sparse(&[1, 0, 2]), [0xFE, 0x00, 0xFD]); // lone zero = 0xFF, negated
+    }
+    #[test] fn v2_sparse_shrinks_scattered_zeros() {
+        // Text with a lone 0x00 every 10 bytes: 10% smaller than v1 and plain v2.
+        let v: Vec<u8> = (0..10_000).map(|i| if i % 10 == 9 { 0x00 } else { b'a' + (i % 26) as u8 }).collect();
+        assert_eq!(compress(&v).len(), 11_000);
+        assert_eq!(compress_v2(&v).len(), 11_000);
+        assert_eq!(compress_v2_sparse(&v).len(), 10_000);
+    }
+    #[cfg(target_pointer_width = "64")]
+    #[test] fn v2_malformed() {
+        assert_eq!(decompress_v2(&[0xFF]), Err(Error::TruncatedVarint));
+        assert_eq!(decompress_v2(&[0xFF, 0x80 ^ 0xFF]), Err(Error::TruncatedVarint));
+        let mut huge = vec![0xFF, 0x00, 0x00, 0x00]; // negated marker + 3 continuation bytes
+        huge.extend_from_slice(&[0xFF; 6]);
+        huge.push(0x7F); // bits past 64
+        assert_eq!(decompress_v2(&huge), Err(Error::TruncatedVarint));
+        // Largest representable run: (usize::MAX >> 1) + 1 bytes.
+        let mut big = vec![0xFF, 0x00, 0x00, 0x00];
+        big.extend_from_slice(&[0xFF; 6]);
+        big.push(0x01);
+        assert_eq!(decompress_v2(&big), Err(Error::OutputTooLarge { max: DEFAULT_MAX_OUTPUT }));
+        // Just past the cap: a zero run of DEFAULT_MAX_OUTPUT + 1 bytes.
+        let mut v = (DEFAULT_MAX_OUTPUT << 1) as u64;
+        let mut bomb = vec![0x00];
+        while v >= 0x80 { bomb.push(v as u8 | 0x80); v >>= 7; }
+        bomb.push(v as u8);
+        for b in &mut bomb[..4] { *b ^= 0xFF; }
+        assert_eq!(decompress_v2(&bomb), Err(Error::OutputTooLarge { max: DEFAULT_MAX_OUTPUT }));
+    }
+}